@@ -0,0 +1,238 @@
+pub mod models;
+
+use std::{
+    collections::HashMap,
+    sync::Mutex
+};
+use actix_web::{
+    Responder,
+    Result,
+    Route,
+    web::{
+        Data,
+        Json,
+        Path,
+        Query,
+        get
+    }
+};
+use chrono::Utc;
+use regex::Regex;
+use lazy_static::lazy_static;
+use crate::{
+    api::search::{
+        SearchHandler,
+        models::search::SearchQuery
+    },
+    config::{
+        Config,
+        handle::ConfigHandle
+    },
+    database::{
+        DatabasePool,
+        dbo::search::SearchDatabase
+    }
+};
+use self::models::{
+    PostView,
+    Search,
+    SearchResponse,
+    SearchType
+};
+
+lazy_static! {
+    static ref COMMUNITY_HANDLE : Regex = Regex::new(r"^\w+@[\w\-\.]+$").unwrap();
+}
+
+/**
+ * A search in the same shape as Lemmy's own `/api/v3/search`, so that Lemmy apps
+ * can search every crawled instance without any changes.  Ids are rewritten to
+ * those of the preferred instance, which is the first part of the path, for apps
+ * that can be given a path, or else the crawler's seed instance.
+ */
+pub struct LemmyCompatHandler {
+    pub routes : HashMap<String, Route>
+}
+
+impl LemmyCompatHandler {
+
+    pub fn new() -> Self {
+        let mut routes = HashMap::<String, Route>::new();
+        routes.insert("/api/v3/search".to_string(), get().to(Self::search));
+        routes.insert("/{instance}/api/v3/search".to_string(), get().to(Self::search_instance));
+
+        Self {
+            routes
+        }
+    }
+
+    pub async fn search<'a>(
+        pool : Data<Mutex<DatabasePool>>,
+        config : Data<ConfigHandle>,
+        search : Query<Search>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+        let config = config.get();
+
+        let search = search.into_inner();
+        let preferred_instance = search.preferred_instance.clone()
+            .unwrap_or_else(|| config.crawler.seed_instance.clone());
+
+        let response = Self::get_search_response(pool, &config, search, preferred_instance)
+            .await?;
+
+        Ok(
+            Json(response)
+                .customize()
+                .insert_header(("cache-control", "public, max-age=86400"))
+        )
+    }
+
+    pub async fn search_instance<'a>(
+        pool : Data<Mutex<DatabasePool>>,
+        config : Data<ConfigHandle>,
+        instance : Path<String>,
+        search : Query<Search>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+        let config = config.get();
+
+        let search = search.into_inner();
+        let preferred_instance = search.preferred_instance.clone()
+            .unwrap_or_else(|| instance.into_inner());
+
+        let response = Self::get_search_response(pool, &config, search, preferred_instance)
+            .await?;
+
+        Ok(
+            Json(response)
+                .customize()
+                .insert_header(("cache-control", "public, max-age=86400"))
+        )
+    }
+
+    /**
+     * Turns Lemmy's parameters into a query for `/search`, with its filters in
+     * place of the community, author and time period.
+     */
+    async fn get_search_response(
+        pool : DatabasePool,
+        config : &Config,
+        search : Search,
+        preferred_instance : String
+    ) -> Result<SearchResponse> {
+        let type_ = search.type_.unwrap_or_default();
+        let mut response = SearchResponse {
+            type_,
+            ..Default::default()
+        };
+        if !type_.includes_posts() {
+            return Ok(response);
+        }
+
+        let preferred_instance = preferred_instance.trim_start_matches("https://")
+            .trim_end_matches('/')
+            .to_string();
+        let preferred_instance_actor_id = format!("https://{}/", preferred_instance);
+
+        let mut query = match type_ {
+            SearchType::Url => format!("url:{}", search.q.trim()),
+            _ => search.q.trim().to_string()
+        };
+
+        let search_database = SearchDatabase::new(pool.clone());
+
+        // Lemmy's ids are only known for communities and authors with posts that
+        // were crawled on the preferred instance, so any others have no results.
+        let community = match (search.community_id, &search.community_name) {
+            (Some(community_id), _) => match search_database.get_community_handle(community_id, &preferred_instance_actor_id)
+                .await
+                .map_err(|err| {
+                    actix_web::error::ErrorInternalServerError(err)
+                })? {
+                    Some(handle) => Some(handle),
+                    None => return Ok(response)
+                },
+            (None, Some(community_name)) => match Self::get_community_handle(community_name, &preferred_instance) {
+                Some(handle) => Some(handle),
+                None => return Err(actix_web::error::ErrorBadRequest("Invalid community name."))
+            },
+            (None, None) => None
+        };
+        if let Some(community) = community {
+            query += &format!(" community:!{}", community);
+        }
+
+        if let Some(creator_id) = search.creator_id {
+            match search_database.get_author_handle(creator_id, &preferred_instance_actor_id)
+                .await
+                .map_err(|err| {
+                    actix_web::error::ErrorInternalServerError(err)
+                })? {
+                    Some(handle) => query += &format!(" author:@{}", handle),
+                    None => return Ok(response)
+                }
+        }
+
+        if let Some(period) = search.sort.and_then(|sort| sort.get_period()) {
+            query += &format!(" after:{}", (Utc::now() - period).format("%Y-%m-%d"));
+        }
+
+        let search_query = SearchQuery {
+            query,
+            preferred_instance,
+            sort : search.sort.map(|sort| sort.get_sort()),
+            page : search.page,
+            page_size : search.limit,
+            ..Default::default()
+        };
+
+        response.posts = SearchHandler::query_posts(pool, config, &search_query)
+            .await?
+            .posts
+            .into_iter()
+            .map(|post| {
+                PostView::new(post, &preferred_instance_actor_id)
+            })
+            .collect();
+
+        Ok(response)
+    }
+
+    /**
+     * The handle of a community given by name, on the preferred instance unless it
+     * names another one, as long as it can't add anything else to the query.
+     */
+    fn get_community_handle(
+        community_name : &str,
+        preferred_instance : &str
+    ) -> Option<String> {
+        let handle = if community_name.contains('@') {
+            community_name.to_string()
+        } else {
+            format!("{}@{}", community_name, preferred_instance)
+        };
+        COMMUNITY_HANDLE.is_match(&handle)
+            .then_some(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LemmyCompatHandler;
+
+    #[test]
+    fn only_accepts_community_names() {
+        assert_eq!(
+            LemmyCompatHandler::get_community_handle("rust", "lemmy.ml"),
+            Some("rust@lemmy.ml".to_string())
+        );
+        assert_eq!(
+            LemmyCompatHandler::get_community_handle("rust@lemmy.world", "lemmy.ml"),
+            Some("rust@lemmy.world".to_string())
+        );
+        assert_eq!(LemmyCompatHandler::get_community_handle("rust author:@alice@lemmy.ml", "lemmy.ml"), None);
+        assert_eq!(LemmyCompatHandler::get_community_handle("rust@lemmy.ml lang:de", "lemmy.ml"), None);
+        assert_eq!(LemmyCompatHandler::get_community_handle("rust", "lemmy.ml site:example.com"), None);
+    }
+}
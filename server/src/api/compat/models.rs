@@ -0,0 +1,345 @@
+use chrono::{
+    DateTime,
+    Duration,
+    Utc
+};
+use serde::{
+    Serialize,
+    Deserialize
+};
+use url::Url;
+use crate::api::search::models::search::{
+    SearchPost,
+    SearchSort
+};
+
+/**
+ * The parameters of Lemmy's `/api/v3/search`.  Lemmy's `listing_type` and `auth`
+ * are accepted but ignored, as every instance is searched and nobody is logged in.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Search {
+    pub q : String,
+    pub community_id : Option<i64>,
+    /// Either `name`, for a community on the preferred instance, or `name@instance`.
+    pub community_name : Option<String>,
+    pub creator_id : Option<i64>,
+    pub type_ : Option<SearchType>,
+    pub sort : Option<SortType>,
+    pub page : Option<i32>,
+    pub limit : Option<i32>,
+    /// Not part of Lemmy's API, the instance whose ids are returned.  Defaults to
+    /// the one in the path, or else the crawler's seed instance.
+    pub preferred_instance : Option<String>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum SearchType {
+    #[default]
+    All,
+    Comments,
+    Posts,
+    Communities,
+    Users,
+    Url
+}
+
+impl SearchType {
+    /**
+     * Only posts are indexed, so nothing else is ever returned.
+     */
+    pub fn includes_posts(
+        &self
+    ) -> bool {
+        matches!(self, Self::All | Self::Posts | Self::Url)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SortType {
+    New,
+    NewComments,
+    MostComments,
+    TopHour,
+    TopSixHour,
+    TopTwelveHour,
+    TopDay,
+    TopWeek,
+    TopMonth,
+    TopThreeMonths,
+    TopSixMonths,
+    TopNineMonths,
+    TopYear,
+    TopAll,
+    /// Anything that can't be matched, such as `Hot` or `Active`, is ordered by
+    /// relevance instead.
+    #[serde(other)]
+    Other
+}
+
+impl SortType {
+    pub fn get_sort(
+        &self
+    ) -> SearchSort {
+        match self {
+            Self::New | Self::NewComments => SearchSort::New,
+            Self::MostComments => SearchSort::Comments,
+            Self::Other => SearchSort::Relevance,
+            _ => SearchSort::Top
+        }
+    }
+
+    /**
+     * How far back the top posts are taken from.  Dates can only be searched by
+     * the day, so the shorter periods all cover since the start of yesterday.
+     */
+    pub fn get_period(
+        &self
+    ) -> Option<Duration> {
+        match self {
+            Self::TopHour | Self::TopSixHour | Self::TopTwelveHour | Self::TopDay => Some(Duration::days(1)),
+            Self::TopWeek => Some(Duration::weeks(1)),
+            Self::TopMonth => Some(Duration::days(30)),
+            Self::TopThreeMonths => Some(Duration::days(91)),
+            Self::TopSixMonths => Some(Duration::days(182)),
+            Self::TopNineMonths => Some(Duration::days(274)),
+            Self::TopYear => Some(Duration::days(365)),
+            _ => None
+        }
+    }
+}
+
+/**
+ * Comments, communities and users are never searched, so those lists are always
+ * empty.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SearchResponse {
+    pub type_ : SearchType,
+    pub comments : Vec<serde_json::Value>,
+    pub posts : Vec<PostView>,
+    pub communities : Vec<serde_json::Value>,
+    pub users : Vec<serde_json::Value>
+}
+
+/**
+ * A post in the same shape as Lemmy's.  The ids are those of the preferred
+ * instance, and anything that isn't indexed is left as Lemmy's default, or 0 for
+ * an id that isn't known.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostView {
+    pub post : Post,
+    pub creator : Person,
+    pub community : Community,
+    pub creator_banned_from_community : bool,
+    pub counts : PostAggregates,
+    pub subscribed : SubscribedType,
+    pub saved : bool,
+    pub read : bool,
+    pub creator_blocked : bool,
+    pub my_vote : Option<i16>,
+    pub unread_comments : i64
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Post {
+    pub id : i64,
+    pub name : String,
+    pub url : Option<String>,
+    /// Only the snippet of the body around the matches.
+    pub body : Option<String>,
+    pub creator_id : i64,
+    pub community_id : i64,
+    pub removed : bool,
+    pub locked : bool,
+    pub published : DateTime<Utc>,
+    pub updated : Option<DateTime<Utc>>,
+    pub deleted : bool,
+    pub nsfw : bool,
+    pub embed_title : Option<String>,
+    pub embed_description : Option<String>,
+    pub thumbnail_url : Option<String>,
+    pub ap_id : String,
+    pub local : bool,
+    pub embed_video_url : Option<String>,
+    pub language_id : i32,
+    pub featured_community : bool,
+    pub featured_local : bool
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Person {
+    pub id : i64,
+    pub name : String,
+    pub display_name : Option<String>,
+    pub avatar : Option<String>,
+    pub banned : bool,
+    pub published : DateTime<Utc>,
+    pub updated : Option<DateTime<Utc>>,
+    pub actor_id : String,
+    pub bio : Option<String>,
+    pub local : bool,
+    pub banner : Option<String>,
+    pub deleted : bool,
+    pub matrix_user_id : Option<String>,
+    pub admin : bool,
+    pub bot_account : bool,
+    pub ban_expires : Option<DateTime<Utc>>,
+    pub instance_id : i64
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Community {
+    pub id : i64,
+    pub name : String,
+    pub title : String,
+    pub description : Option<String>,
+    pub removed : bool,
+    pub published : DateTime<Utc>,
+    pub updated : Option<DateTime<Utc>>,
+    pub deleted : bool,
+    pub nsfw : bool,
+    pub actor_id : String,
+    pub local : bool,
+    pub icon : Option<String>,
+    pub banner : Option<String>,
+    pub hidden : bool,
+    pub posting_restricted_to_mods : bool,
+    pub instance_id : i64
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PostAggregates {
+    pub id : i64,
+    pub post_id : i64,
+    pub comments : i64,
+    pub score : i64,
+    pub upvotes : i64,
+    pub downvotes : i64,
+    pub published : DateTime<Utc>,
+    pub newest_comment_time_necro : DateTime<Utc>,
+    pub newest_comment_time : DateTime<Utc>,
+    pub featured_community : bool,
+    pub featured_local : bool,
+    pub hot_rank : i32,
+    pub hot_rank_active : i32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum SubscribedType {
+    Subscribed,
+    NotSubscribed,
+    Pending
+}
+
+impl PostView {
+
+    pub fn new(
+        post : SearchPost,
+        preferred_instance_actor_id : &str
+    ) -> Self {
+        let is_local = |actor_id : &str| {
+            Self::get_host(actor_id) == Self::get_host(preferred_instance_actor_id)
+        };
+
+        let post_id = post.remote_id;
+        let creator_id = post.author_remote_id.unwrap_or(0);
+        let community_id = post.community_remote_id.unwrap_or(0);
+        let published = post.published.unwrap_or(DateTime::UNIX_EPOCH);
+        let score = post.score.unwrap_or(0);
+
+        Self {
+            post : Post {
+                id : post_id,
+                name : post.name,
+                url : post.url,
+                body : post.snippet,
+                creator_id,
+                community_id,
+                removed : false,
+                locked : false,
+                published,
+                updated : None,
+                deleted : false,
+                nsfw : false,
+                embed_title : None,
+                embed_description : None,
+                thumbnail_url : None,
+                local : is_local(&post.ap_id),
+                ap_id : post.ap_id,
+                embed_video_url : None,
+                language_id : 0,
+                featured_community : false,
+                featured_local : false
+            },
+            creator : Person {
+                id : creator_id,
+                name : post.author.name,
+                display_name : post.author.display_name,
+                avatar : post.author.avatar,
+                banned : false,
+                published : DateTime::UNIX_EPOCH,
+                updated : None,
+                local : is_local(&post.author.actor_id),
+                actor_id : post.author.actor_id,
+                bio : None,
+                banner : None,
+                deleted : false,
+                matrix_user_id : None,
+                admin : false,
+                bot_account : false,
+                ban_expires : None,
+                instance_id : 0
+            },
+            community : Community {
+                id : community_id,
+                title : post.community.title.unwrap_or_else(|| post.community.name.clone()),
+                name : post.community.name,
+                description : None,
+                removed : false,
+                published : DateTime::UNIX_EPOCH,
+                updated : None,
+                deleted : false,
+                nsfw : false,
+                local : is_local(&post.community.actor_id),
+                actor_id : post.community.actor_id,
+                icon : post.community.icon,
+                banner : None,
+                hidden : false,
+                posting_restricted_to_mods : false,
+                instance_id : 0
+            },
+            creator_banned_from_community : false,
+            counts : PostAggregates {
+                id : post_id,
+                post_id,
+                comments : post.comments.unwrap_or(0),
+                score,
+                upvotes : score.max(0),
+                downvotes : (-score).max(0),
+                published,
+                newest_comment_time_necro : published,
+                newest_comment_time : published,
+                featured_community : false,
+                featured_local : false,
+                hot_rank : 0,
+                hot_rank_active : 0
+            },
+            subscribed : SubscribedType::NotSubscribed,
+            saved : false,
+            read : false,
+            creator_blocked : false,
+            my_vote : None,
+            unread_comments : 0
+        }
+    }
+
+    fn get_host(
+        actor_id : &str
+    ) -> Option<String> {
+        Url::parse(actor_id).ok()?
+            .host_str()
+            .map(|host| host.to_string())
+    }
+}
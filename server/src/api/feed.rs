@@ -0,0 +1,297 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex
+};
+use actix_web::{
+    HttpRequest,
+    Responder,
+    Result,
+    Route,
+    web::{
+        Data,
+        Query,
+        get
+    }
+};
+use chrono::{
+    DateTime,
+    Utc
+};
+use serde::{
+    Serialize,
+    Deserialize
+};
+use url::Url;
+use crate::{
+    api::{
+        opensearch::OpenSearchHandler,
+        search::{
+            SearchHandler,
+            models::search::{
+                SearchPost,
+                SearchQuery,
+                SearchResult,
+                SearchSort
+            }
+        }
+    },
+    config::handle::ConfigHandle,
+    database::DatabasePool
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FeedQuery {
+    pub query : String,
+    pub preferred_instance : String,
+    /// A comma separated list of ISO 639-1 language codes to limit posts to.
+    pub languages : Option<String>
+}
+
+/**
+ * The newest posts that match a query, as an RSS or Atom feed, so that a topic can
+ * be followed from a feed reader.  Every link points to the preferred instance.
+ */
+pub struct FeedHandler {
+    pub routes : HashMap<String, Route>
+}
+
+/**
+ * Everything about the feed that's the same for both formats.
+ */
+struct Feed {
+    title : String,
+    query : String,
+    self_url : String,
+    results_url : String,
+    preferred_instance_actor_id : String,
+    updated : DateTime<Utc>,
+    posts : Vec<SearchPost>
+}
+
+impl FeedHandler {
+
+    const FEED_SIZE : i32 = 25;
+
+    pub fn new() -> Self {
+        let mut routes = HashMap::<String, Route>::new();
+        routes.insert("/search.rss".to_string(), get().to(Self::rss));
+        routes.insert("/search.atom".to_string(), get().to(Self::atom));
+
+        Self {
+            routes
+        }
+    }
+
+    pub async fn rss<'a>(
+        request : HttpRequest,
+        pool : Data<Mutex<DatabasePool>>,
+        config : Data<ConfigHandle>,
+        feed_query : Query<FeedQuery>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let feed = Self::get_feed(&request, pool, &config, &feed_query)
+            .await?;
+
+        let items = feed.posts.iter().map(|post| {
+            let link = Self::get_post_url(post, &feed.preferred_instance_actor_id);
+            let published = post.published.map(|published| {
+                format!("\n            <pubDate>{}</pubDate>", published.to_rfc2822())
+            }).unwrap_or_default();
+
+            format!(r#"
+        <item>
+            <title>{title}</title>
+            <link>{link}</link>
+            <guid isPermaLink="true">{link}</guid>{published}
+            <dc:creator>{author}</dc:creator>
+            <category>{community}</category>
+            <description>{description}</description>
+        </item>"#,
+                title = OpenSearchHandler::escape_xml(&post.name),
+                link = OpenSearchHandler::escape_xml(&link),
+                published = published,
+                author = OpenSearchHandler::escape_xml(post.author.display_name.as_ref().unwrap_or(&post.author.name)),
+                community = OpenSearchHandler::escape_xml(post.community.title.as_ref().unwrap_or(&post.community.name)),
+                description = OpenSearchHandler::escape_xml(&Self::get_summary(post))
+            )
+        }).collect::<String>();
+
+        let rss = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <channel>
+        <title>{title}</title>
+        <link>{results_url}</link>
+        <description>The newest posts matching '{query}'</description>
+        <lastBuildDate>{updated}</lastBuildDate>
+        <atom:link href="{self_url}" rel="self" type="application/rss+xml"/>{items}
+    </channel>
+</rss>
+"#,
+            title = OpenSearchHandler::escape_xml(&feed.title),
+            query = OpenSearchHandler::escape_xml(&feed.query),
+            results_url = OpenSearchHandler::escape_xml(&feed.results_url),
+            updated = feed.updated.to_rfc2822(),
+            self_url = OpenSearchHandler::escape_xml(&feed.self_url),
+            items = items
+        );
+
+        Ok(
+            rss.customize()
+                .insert_header(("content-type", "application/rss+xml; charset=utf-8"))
+                .insert_header(("cache-control", "public, max-age=900"))
+        )
+    }
+
+    pub async fn atom<'a>(
+        request : HttpRequest,
+        pool : Data<Mutex<DatabasePool>>,
+        config : Data<ConfigHandle>,
+        feed_query : Query<FeedQuery>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let feed = Self::get_feed(&request, pool, &config, &feed_query)
+            .await?;
+
+        let entries = feed.posts.iter().map(|post| {
+            let link = Self::get_post_url(post, &feed.preferred_instance_actor_id);
+            let author_url = SearchHandler::get_preferred_url(
+                &post.author.actor_id, "u", &post.author.name, &feed.preferred_instance_actor_id
+            );
+            // Posts are never fetched again once indexed, so they're as up to date
+            // as when they were published.
+            let updated = post.published.unwrap_or(feed.updated)
+                .to_rfc3339();
+
+            format!(r#"
+    <entry>
+        <title>{title}</title>
+        <id>{link}</id>
+        <link href="{link}"/>
+        <updated>{updated}</updated>
+        <published>{updated}</published>
+        <author>
+            <name>{author}</name>
+            <uri>{author_url}</uri>
+        </author>
+        <category term="{community}"/>
+        <summary>{summary}</summary>
+    </entry>"#,
+                title = OpenSearchHandler::escape_xml(&post.name),
+                link = OpenSearchHandler::escape_xml(&link),
+                updated = updated,
+                author = OpenSearchHandler::escape_xml(post.author.display_name.as_ref().unwrap_or(&post.author.name)),
+                author_url = OpenSearchHandler::escape_xml(&author_url),
+                community = OpenSearchHandler::escape_xml(post.community.title.as_ref().unwrap_or(&post.community.name)),
+                summary = OpenSearchHandler::escape_xml(&Self::get_summary(post))
+            )
+        }).collect::<String>();
+
+        let atom = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+    <title>{title}</title>
+    <subtitle>The newest posts matching '{query}'</subtitle>
+    <id>{self_url}</id>
+    <link rel="self" type="application/atom+xml" href="{self_url}"/>
+    <link rel="alternate" type="text/html" href="{results_url}"/>
+    <updated>{updated}</updated>{entries}
+</feed>
+"#,
+            title = OpenSearchHandler::escape_xml(&feed.title),
+            query = OpenSearchHandler::escape_xml(&feed.query),
+            self_url = OpenSearchHandler::escape_xml(&feed.self_url),
+            results_url = OpenSearchHandler::escape_xml(&feed.results_url),
+            updated = feed.updated.to_rfc3339(),
+            entries = entries
+        );
+
+        Ok(
+            atom.customize()
+                .insert_header(("content-type", "application/atom+xml; charset=utf-8"))
+                .insert_header(("cache-control", "public, max-age=900"))
+        )
+    }
+
+    /**
+     * Runs the same search as the UI, filters and all, but always for the newest
+     * posts.
+     */
+    async fn get_feed(
+        request : &HttpRequest,
+        pool : DatabasePool,
+        config : &ConfigHandle,
+        feed_query : &FeedQuery
+    ) -> Result<Feed> {
+        let config = config.get();
+
+        let search_query = SearchQuery {
+            query : feed_query.query.clone(),
+            preferred_instance : feed_query.preferred_instance.clone(),
+            languages : feed_query.languages.clone(),
+            sort : Some(SearchSort::New),
+            page_size : Some(Self::FEED_SIZE),
+            ..Default::default()
+        };
+
+        let SearchResult { posts, .. } = SearchHandler::query_posts(pool, &config, &search_query)
+            .await?;
+
+        let base_url = OpenSearchHandler::get_base_url(&config.site, request);
+
+        let mut results_params = vec![
+            ("query", feed_query.query.as_str()),
+            ("preferred_instance", feed_query.preferred_instance.as_str())
+        ];
+        if let Some(languages) = &feed_query.languages {
+            results_params.push(("languages", languages.as_str()));
+        }
+        let results_url = Url::parse_with_params(&format!("{}/results", base_url), &results_params)
+            .map(|url| {
+                url.to_string()
+            })
+            .unwrap_or_else(|_| {
+                format!("{}/", base_url)
+            });
+
+        let updated = posts.iter()
+            .filter_map(|post| {
+                post.published
+            })
+            .max()
+            .unwrap_or_else(Utc::now);
+
+        Ok(Feed {
+            title : format!("{}: {}", config.site.name, feed_query.query),
+            query : feed_query.query.clone(),
+            self_url : format!("{}{}", base_url, request.uri()),
+            results_url,
+            // The preferred instance is sent without the https://, re-add it back.
+            preferred_instance_actor_id : format!("https://{}/", feed_query.preferred_instance),
+            updated,
+            posts
+        })
+    }
+
+    fn get_post_url(
+        post : &SearchPost,
+        preferred_instance_actor_id : &str
+    ) -> String {
+        format!("{}post/{}", preferred_instance_actor_id, post.remote_id)
+    }
+
+    /**
+     * The snippet, followed by the link that was posted if there is one.
+     */
+    fn get_summary(
+        post : &SearchPost
+    ) -> String {
+        let snippet = post.snippet.clone()
+            .unwrap_or_default();
+        match &post.url {
+            Some(url) if snippet.is_empty() => url.clone(),
+            Some(url) => format!("{}\n\n{}", snippet, url),
+            None => snippet
+        }
+    }
+}
@@ -21,6 +21,11 @@ use super::models::{
         PostData, 
         PostListRequest, 
         PostListResponse, 
+    },
+    community::{
+        CommunityView,
+        CommunityListRequest,
+        CommunityListResponse
     }
 };
 
@@ -108,6 +113,26 @@ impl Fetcher {
             })
     }
 
+    pub async fn fetch_communities(
+        &self,
+        page : i32
+    ) -> Result<Vec<CommunityView>> {
+        let params = CommunityListRequest {
+            type_: Some(super::models::common::ListingType::All),
+            sort: Some(SortType::Old),
+            limit: Self::DEFAULT_LIMIT,
+            page
+        };
+
+        let url = self.get_url("/api/v3/community/list");
+
+        self.fetch_json(&url, params)
+            .await
+            .map(|view: CommunityListResponse| {
+                view.communities
+            })
+    }
+
     async fn fetch_json<T, R>(
         &self,
         url : &str,
@@ -5,8 +5,14 @@ use serde::{
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Author {
+    /// The author's id on the instance that they were fetched from, which is
+    /// different on every instance.
+    #[serde(default)]
+    pub id : Option<i64>,
     pub actor_id : String,
     pub avatar : Option<String>,
     pub name : String,
-    pub display_name : Option<String>
+    pub display_name : Option<String>,
+    #[serde(default)]
+    pub bio : Option<String>
 }
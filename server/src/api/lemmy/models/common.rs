@@ -1,6 +1,12 @@
+use chrono::{
+    DateTime, 
+    NaiveDateTime, 
+    Utc
+};
 use serde::{
     Serialize, 
-    Deserialize
+    Deserialize, 
+    Deserializer
 };
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -15,3 +21,32 @@ pub enum ListingType {
     Subscribed,
     Local
 }
+
+/**
+ * Lemmy has sent its timestamps both with and without a timezone depending on
+ * the version of the instance, where those without one are in UTC.  Anything
+ * that can't be parsed is treated as missing rather than failing the whole
+ * response.
+ */
+pub fn deserialize_timestamp<'de, D>(
+    deserializer : D
+) -> Result<Option<DateTime<Utc>>, D::Error> 
+where
+    D : Deserializer<'de>
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+
+    Ok(value.and_then(|value| {
+        DateTime::parse_from_rfc3339(&value)
+            .map(|timestamp| {
+                timestamp.with_timezone(&Utc)
+            })
+            .or_else(|_| {
+                NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S%.f")
+                    .map(|timestamp| {
+                        timestamp.and_utc()
+                    })
+            })
+            .ok()
+    }))
+}
@@ -3,10 +3,51 @@ use serde::{
     Deserialize
 };
 
+use super::common::{
+    SortType, 
+    ListingType
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CommunityListRequest {
+    pub type_ : Option<ListingType>,
+    pub sort : Option<SortType>,
+    pub limit : i32,
+    pub page : i32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CommunityListResponse {
+    pub communities : Vec<CommunityView>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CommunityView {
+    pub community : Community,
+    pub counts : CommunityCounts
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Community {
+    /// The community's id on the instance that it was fetched from, which is
+    /// different on every instance.
+    #[serde(default)]
+    pub id : Option<i64>,
     pub actor_id : String,
     pub icon : Option<String>,
     pub name : String,
-    pub title : Option<String>
+    pub title : Option<String>,
+    #[serde(default)]
+    pub description : Option<String>,
+    #[serde(default)]
+    pub nsfw : Option<bool>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CommunityCounts {
+    pub subscribers : i64,
+    pub posts : i64,
+    pub comments : i64,
+    #[serde(default)]
+    pub users_active_month : i64
 }
@@ -7,6 +7,10 @@ use serde::{
 pub struct LemmyId {
     pub post_remote_id : i64,
     pub post_actor_id : String,
-    pub instance_actor_id : String
+    pub instance_actor_id : String,
+    /// The id of the post's community on the instance.
+    pub community_remote_id : Option<i64>,
+    /// The id of the post's author on the instance.
+    pub creator_remote_id : Option<i64>
 }
 
@@ -0,0 +1,11 @@
+use serde::{
+    Serialize, 
+    Deserialize
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Language {
+    pub id : i32,
+    pub code : String,
+    pub name : String
+}
@@ -3,5 +3,6 @@ pub mod common;
 pub mod comment;
 pub mod community;
 pub mod id;
+pub mod language;
 pub mod post;
 pub mod site;
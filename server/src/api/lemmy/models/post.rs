@@ -1,3 +1,7 @@
+use chrono::{
+    DateTime, 
+    Utc
+};
 use serde::{
     Serialize, 
     Deserialize
@@ -6,7 +10,8 @@ use serde::{
 use super::{
     common::{
         SortType, 
-        ListingType
+        ListingType,
+        deserialize_timestamp
     }, 
     community::Community, 
     author::Author
@@ -43,7 +48,29 @@ pub struct Post {
     pub body : Option<String>,
     pub removed : Option<bool>,
     pub deleted : Option<bool>,
-    pub language_id : i32
+    pub language_id : i32,
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    pub published : Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    pub updated : Option<DateTime<Utc>>,
+    /// The ISO 639-1 code for `language_id`.  This isn't part of Lemmy's API,
+    /// instead it's resolved by the crawler using the instance's languages.
+    #[serde(default)]
+    pub language : Option<String>,
+    /// The ISO 639-1 code of the language that the post appears to be written
+    /// in, only detected when the post's author left its language undetermined.
+    #[serde(default)]
+    pub detected_language : Option<String>,
+    /// How confident the detector was in `detected_language`, from 0 to 1.
+    #[serde(default)]
+    pub language_confidence : Option<f32>,
+    /// The host of `url`, lowercased and without any leading 'www.'.
+    #[serde(default)]
+    pub domain : Option<String>,
+    /// `url` without its scheme or fragment, so that links to the same page
+    /// compare equal.
+    #[serde(default)]
+    pub normalized_url : Option<String>
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
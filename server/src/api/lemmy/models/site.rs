@@ -3,12 +3,15 @@ use serde::{
     Deserialize
 };
 
+use super::language::Language;
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct SiteRequest;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct SiteResponse {
-    pub site_view : SiteView
+    pub site_view : SiteView,
+    pub all_languages : Option<Vec<Language>>
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
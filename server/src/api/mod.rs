@@ -1,2 +1,7 @@
+pub mod compat;
+pub mod feed;
 pub mod lemmy;
+pub mod opensearch;
+pub mod saved_search;
 pub mod search;
+pub mod v1;
@@ -0,0 +1,182 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex
+};
+use actix_web::{
+    HttpRequest,
+    Responder,
+    Result,
+    Route,
+    web::{
+        Data,
+        Json,
+        Query,
+        get
+    }
+};
+use serde::{
+    Serialize,
+    Deserialize
+};
+use crate::{
+    api::search::{
+        SearchHandler,
+        models::search::SuggestQuery
+    },
+    config::{
+        Site,
+        handle::ConfigHandle
+    },
+    database::DatabasePool
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OpenSearchSuggestQuery {
+    pub q : String
+}
+
+/**
+ * Lets browsers add the site as one of their search engines, searching it straight
+ * from the address bar and offering completions as the user types.
+ *
+ * See https://github.com/dewitt/opensearch for the description document and the
+ * suggestions format.
+ */
+pub struct OpenSearchHandler {
+    pub routes : HashMap<String, Route>
+}
+
+impl OpenSearchHandler {
+
+    pub fn new() -> Self {
+        let mut routes = HashMap::<String, Route>::new();
+        routes.insert("/opensearch.xml".to_string(), get().to(Self::description));
+        routes.insert("/opensearch/suggest".to_string(), get().to(Self::suggest));
+
+        Self {
+            routes
+        }
+    }
+
+    /**
+     * The OpenSearch description, built from the `site` section of the config.
+     */
+    pub async fn description<'a>(
+        request : HttpRequest,
+        config : Data<ConfigHandle>
+    ) -> Result<impl Responder> {
+        let site = config.get().site;
+
+        let base_url = Self::get_base_url(&site, &request);
+        let icon = if site.icon.contains("://") {
+            site.icon.clone()
+        } else {
+            format!("{}/{}", base_url, site.icon.trim_start_matches('/'))
+        };
+
+        let description = format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/" xmlns:moz="http://www.mozilla.org/2006/browser/search/">
+    <ShortName>{name}</ShortName>
+    <Description>Search for posts on Lemmy with {name}</Description>
+    <InputEncoding>UTF-8</InputEncoding>
+    <Image type="{icon_type}">{icon}</Image>
+    <Url type="text/html" method="get" template="{base_url}/results?query={{searchTerms}}"/>
+    <Url type="application/x-suggestions+json" method="get" template="{base_url}/opensearch/suggest?q={{searchTerms}}"/>
+    <Url type="application/opensearchdescription+xml" rel="self" template="{base_url}/opensearch.xml"/>
+    <moz:SearchForm>{base_url}/</moz:SearchForm>
+</OpenSearchDescription>
+"#,
+            name = Self::escape_xml(&site.name),
+            icon_type = Self::get_image_type(&icon),
+            icon = Self::escape_xml(&icon),
+            base_url = Self::escape_xml(&base_url)
+        );
+
+        Ok(
+            description
+                .customize()
+                .insert_header(("content-type", "application/opensearchdescription+xml"))
+                .insert_header(("cache-control", "public, max-age=86400"))
+        )
+    }
+
+    /**
+     * Completions in the OpenSearch Suggestions format, i.e. the query followed by
+     * a list of completed queries.  These come from the same words as `/suggest`.
+     */
+    pub async fn suggest<'a>(
+        pool : Data<Mutex<DatabasePool>>,
+        suggest_query : Query<OpenSearchSuggestQuery>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let query = SuggestQuery {
+            prefix : suggest_query.q.clone()
+        };
+
+        // Don't bother completing a term the user has already finished typing.
+        let completions = if query.prefix.ends_with(char::is_whitespace) {
+            Vec::new()
+        } else {
+            SearchHandler::query_suggestions(pool, &query)
+                .await?
+                .into_iter()
+                .map(|suggestion| {
+                    suggestion.text
+                })
+                .collect::<Vec<_>>()
+        };
+
+        Ok(
+            Json((query.prefix, completions))
+                .customize()
+                .insert_header(("content-type", "application/x-suggestions+json"))
+                .insert_header(("cache-control", "public, max-age=3600"))
+        )
+    }
+
+    /**
+     * The configured address of the site, or otherwise the one this request was
+     * made to, without a trailing slash.
+     */
+    pub fn get_base_url(
+        site : &Site,
+        request : &HttpRequest
+    ) -> String {
+        match &site.base_url {
+            Some(base_url) => base_url.trim_end_matches('/')
+                .to_string(),
+            None => {
+                let connection_info = request.connection_info();
+                format!("{}://{}", connection_info.scheme(), connection_info.host())
+            }
+        }
+    }
+
+    fn get_image_type(
+        url : &str
+    ) -> &'static str {
+        let extension = url.rsplit('.')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        match extension.as_str() {
+            "svg" => "image/svg+xml",
+            "ico" => "image/x-icon",
+            "jpg" | "jpeg" => "image/jpeg",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            _ => "image/png"
+        }
+    }
+
+    pub fn escape_xml(
+        value : &str
+    ) -> String {
+        value.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;")
+    }
+}
@@ -0,0 +1,218 @@
+use std::sync::Mutex;
+use actix_web::{
+    Responder,
+    Result,
+    Route,
+    http::StatusCode,
+    web::{
+        Data,
+        Json,
+        Path,
+        delete,
+        get,
+        post
+    }
+};
+use chrono::{
+    DateTime,
+    Utc
+};
+use serde::{
+    Serialize,
+    Deserialize
+};
+use uuid::Uuid;
+use crate::{
+    config::{
+        Config,
+        handle::ConfigHandle
+    },
+    crawler::notifier::SavedSearchNotifier,
+    database::{
+        DatabasePool,
+        dbo::saved_search::SavedSearchDBO,
+        schema::saved_search::SavedSearch
+    },
+    error::LogError
+};
+
+/**
+ * What the webhook expects to be sent.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SavedSearchFormat {
+    /// The matching posts as JSON.
+    #[default]
+    Webhook,
+    /// A plain text message, for an ntfy topic.
+    Ntfy,
+    /// A message for a Matrix room, through a matrix-hookshot generic webhook.
+    Matrix
+}
+
+impl SavedSearchFormat {
+    pub fn as_str(
+        &self
+    ) -> &'static str {
+        match self {
+            Self::Webhook => "webhook",
+            Self::Ntfy => "ntfy",
+            Self::Matrix => "matrix"
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SavedSearchRequest {
+    /// Any query that `/search` accepts, filters included.
+    pub query : String,
+    pub preferred_instance : String,
+    pub webhook_url : String,
+    pub format : Option<SavedSearchFormat>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedSearchResponse {
+    /// Needed to remove the saved search, so keep it somewhere safe.
+    pub id : Uuid,
+    pub query : String,
+    pub preferred_instance : String,
+    pub webhook_url : String,
+    pub format : String,
+    pub created : DateTime<Utc>,
+    pub last_notified : Option<DateTime<Utc>>
+}
+
+impl From<SavedSearch> for SavedSearchResponse {
+    fn from(value : SavedSearch) -> Self {
+        Self {
+            id : value.id,
+            query : value.query,
+            preferred_instance : value.preferred_instance,
+            webhook_url : value.webhook_url,
+            format : value.format,
+            created : value.created,
+            last_notified : value.last_notified
+        }
+    }
+}
+
+/**
+ * Saves a search along with a webhook, which is sent any new posts that match the
+ * search after each crawl.  These are only available when `saved_searches` is
+ * enabled in the config, as they let anyone make the server send requests, and
+ * webhooks are limited to public addresses or else the allowed hosts.
+ */
+pub struct SavedSearchHandler {
+    /// Each saved search has more than one route, so these can't be keyed by path.
+    pub routes : Vec<(String, Route)>
+}
+
+impl SavedSearchHandler {
+
+    pub fn new(config : &Config) -> Self {
+        let mut routes = Vec::<(String, Route)>::new();
+        if config.saved_searches.enabled {
+            routes.push(("/saved-searches".to_string(), post().to(Self::create)));
+            routes.push(("/saved-searches/{id}".to_string(), get().to(Self::retrieve)));
+            routes.push(("/saved-searches/{id}".to_string(), delete().to(Self::delete)));
+        }
+
+        Self {
+            routes
+        }
+    }
+
+    pub async fn create<'a>(
+        pool : Data<Mutex<DatabasePool>>,
+        config : Data<ConfigHandle>,
+        request : Json<SavedSearchRequest>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        if request.query.trim().is_empty() {
+            return Err(actix_web::error::ErrorBadRequest("The query can't be empty."));
+        }
+        if request.preferred_instance.trim().is_empty() {
+            return Err(actix_web::error::ErrorBadRequest("The preferred instance can't be empty."));
+        }
+        SavedSearchNotifier::resolve_webhook(&config.get().saved_searches, &request.webhook_url)
+            .await
+            .map_err(|err| {
+                actix_web::error::ErrorBadRequest(err)
+            })?;
+
+        let saved_search = SavedSearch {
+            id : Uuid::new_v4(),
+            query : request.query.trim().to_string(),
+            preferred_instance : request.preferred_instance.trim().to_string(),
+            webhook_url : request.webhook_url.clone(),
+            format : request.format.unwrap_or_default().as_str().to_string(),
+            created : Utc::now(),
+            last_notified : None
+        };
+
+        SavedSearchDBO::new(pool)
+            .create(&saved_search)
+            .await
+            .log_error("Error while saving a search.", true)
+            .map_err(|err| {
+                actix_web::error::ErrorInternalServerError(err)
+            })?;
+
+        Ok(
+            Json(SavedSearchResponse::from(saved_search))
+                .customize()
+                .with_status(StatusCode::CREATED)
+                .insert_header(("cache-control", "no-store"))
+        )
+    }
+
+    pub async fn retrieve<'a>(
+        pool : Data<Mutex<DatabasePool>>,
+        id : Path<Uuid>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let saved_search = SavedSearchDBO::new(pool)
+            .retrieve(id.into_inner())
+            .await
+            .map_err(|err| {
+                actix_web::error::ErrorInternalServerError(err)
+            })?
+            .ok_or_else(|| {
+                actix_web::error::ErrorNotFound("No such saved search.")
+            })?;
+
+        Ok(
+            Json(SavedSearchResponse::from(saved_search))
+                .customize()
+                .insert_header(("cache-control", "no-store"))
+        )
+    }
+
+    pub async fn delete<'a>(
+        pool : Data<Mutex<DatabasePool>>,
+        id : Path<Uuid>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let deleted = SavedSearchDBO::new(pool)
+            .delete(id.into_inner())
+            .await
+            .map_err(|err| {
+                actix_web::error::ErrorInternalServerError(err)
+            })?;
+
+        if !deleted {
+            return Err(actix_web::error::ErrorNotFound("No such saved search."));
+        }
+
+        Ok(
+            "Deleted"
+                .customize()
+                .insert_header(("cache-control", "no-store"))
+        )
+    }
+}
@@ -1,12 +1,21 @@
 pub mod models;
+pub mod snippet;
+pub mod spelling;
 
+use chrono::{
+    DateTime, 
+    NaiveDate, 
+    Utc
+};
+use base64::{
+    engine::general_purpose::URL_SAFE_NO_PAD, 
+    Engine
+};
 use regex::Regex;
+use url::Url;
 use lazy_static::lazy_static;
 use std::{
-    collections::{
-        HashMap, 
-        HashSet
-    }, 
+    collections::HashMap, 
     sync::Mutex, 
     time::Instant
 };
@@ -24,26 +33,71 @@ use actix_web::{
 use crate::{
     error::LogError,
     api::search::models::search::{
+        AuthorSearchQuery,
+        AuthorSearchResult,
+        CommunitySearchQuery,
+        CommunitySearchResult,
+        DiscussionQuery,
+        SearchDiscussion,
         SearchQuery,
-        SearchResult
+        SearchResult,
+        SearchSuggestion,
+        SuggestQuery
     }, 
     database::{
         dbo::{
             site::SiteDBO, 
-            search::SearchDatabase
+            language::LanguageDBO, 
+            search::{
+                SearchCursor,
+                SearchDatabase,
+                SearchFilters,
+                SearchOptions
+            }
         }, 
         DatabasePool
     }, 
-    crawler::crawler::Crawler, 
-    config::Config
+    crawler::{
+        analyzer::{
+            analyze_query, 
+            detect_query_language,
+            get_domain,
+            normalize_url,
+            IndexField,
+            QueryTerm,
+            SupportedLanguage
+        },
+        crawler::Crawler
+    }, 
+    config::{
+        Config, 
+        handle::ConfigHandle
+    }
 };
 
-use self::models::search::Version;
+use self::{
+    models::search::{
+        SearchPost,
+        Version
+    },
+    snippet::build_snippet,
+    spelling::{
+        expand_query_terms,
+        get_corrections,
+        get_did_you_mean,
+        get_words_to_correct
+    }
+};
 
 lazy_static! {
-    static ref INSTANCE_MATCH : Regex = Regex::new(r" instance:(?P<instance>(https://)?[\w\-\.]+)").unwrap();
-    static ref COMMUNITY_MATCH : Regex = Regex::new(r" community:(?P<community>!\w+@[\w\-\.]+)").unwrap();
-    static ref AUTHOR_MATCH : Regex = Regex::new(r" author:(?P<author>@\w+@[\w\-\.]+)").unwrap();
+    static ref INSTANCE_MATCH : Regex = Regex::new(r"(^|\s)instance:(?P<instance>(https://)?[\w\-\.]+)").unwrap();
+    static ref COMMUNITY_MATCH : Regex = Regex::new(r"(^|\s)community:(?P<community>!\w+@[\w\-\.]+)").unwrap();
+    static ref AUTHOR_MATCH : Regex = Regex::new(r"(^|\s)author:(?P<author>@\w+@[\w\-\.]+)").unwrap();
+    static ref LANGUAGE_MATCH : Regex = Regex::new(r"(^|\s)lang:(?P<language>[a-zA-Z]{2,3})\b").unwrap();
+    static ref SITE_MATCH : Regex = Regex::new(r"(^|\s)site:(?P<site>[\w\-\.]+)").unwrap();
+    static ref URL_MATCH : Regex = Regex::new(r"(^|\s)url:(?P<url>\S+)").unwrap();
+    static ref AFTER_MATCH : Regex = Regex::new(r"(^|\s)after:(?P<date>\d{4}-\d{2}-\d{2})\b").unwrap();
+    static ref BEFORE_MATCH : Regex = Regex::new(r"(^|\s)before:(?P<date>\d{4}-\d{2}-\d{2})\b").unwrap();
 
     static ref COMMUNITY_FORMAT : Regex = Regex::new(r"!(?P<name>\w+)@(?P<instance>[\w\-\.]+)").unwrap();
     static ref AUTHOR_FORMAT : Regex = Regex::new(r"@(?P<name>\w+)@(?P<instance>[\w\-\.]+)").unwrap();
@@ -55,7 +109,10 @@ pub struct SearchHandler {
 
 impl SearchHandler {
 
-    const PAGE_LIMIT : usize = 50;
+    const MAX_FACETS : i64 = 25;
+    const SUGGESTION_LIMIT : i64 = 10;
+    const SIMILAR_WORD_LIMIT : i64 = 10;
+    const FEW_RESULTS : i32 = 10;
 
     pub fn new(config : &Config) -> Self {
         let mut routes = HashMap::<String, Route>::new();
@@ -66,6 +123,11 @@ impl SearchHandler {
         routes.insert("/version".to_string(), get().to(Self::version));
         routes.insert("/search".to_string(), get().to(Self::search));
         routes.insert("/instances".to_string(), get().to(Self::get_instances));
+        routes.insert("/languages".to_string(), get().to(Self::get_languages));
+        routes.insert("/discussions".to_string(), get().to(Self::get_discussions));
+        routes.insert("/suggest".to_string(), get().to(Self::suggest));
+        routes.insert("/search/communities".to_string(), get().to(Self::search_communities));
+        routes.insert("/search/authors".to_string(), get().to(Self::search_authors));
 
         Self {
             routes
@@ -103,17 +165,19 @@ impl SearchHandler {
      * Temporary endpoint to allow for more easily testing the crawler.
      */
     pub async fn crawl<'a>(
-        pool : Data<Mutex<DatabasePool>>
+        pool : Data<Mutex<DatabasePool>>,
+        config : Data<ConfigHandle>
     ) -> Result<impl Responder> {
 
         tokio::spawn(async move {
 
-            let config = Config::load();
+            let config = config.get();
+            let pool = pool.lock().unwrap().clone();
 
             let crawler = Crawler::new(
                 config.crawler.seed_instance.clone(), 
-                config.crawler, 
-                pool.lock().unwrap().clone(), 
+                config.clone(), 
+                pool.clone(), 
                 false
             ).unwrap();
 
@@ -137,66 +201,89 @@ impl SearchHandler {
      */
     pub async fn search<'a>(
         pool : Data<Mutex<DatabasePool>>,
-        search_query: Query<SearchQuery>
+        config : Data<ConfigHandle>,
+        search_query : Query<SearchQuery>
     ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let results = Self::query_posts(pool, &config.get(), &search_query)
+            .await?;
+
+        Ok(
+            Json(results)
+                .customize()
+                .insert_header(("cache-control", "public, max-age=86400"))
+        )
+    }
 
+    /**
+     * Runs the search described by the query, shared by every API that searches
+     * for posts.
+     */
+    pub async fn query_posts(
+        pool : DatabasePool,
+        config : &Config,
+        search_query : &SearchQuery
+    ) -> Result<SearchResult> {
         let start = Instant::now();
 
+        let cursor = match &search_query.cursor {
+            Some(cursor) => Some(Self::decode_cursor(cursor).ok_or_else(|| {
+                actix_web::error::ErrorBadRequest("Invalid cursor.")
+            })?),
+            None => None
+        };
+
         let query = search_query.query.to_owned();
         let mut modified_query = query.clone();
         
         // Extract filters
-        let instance = match INSTANCE_MATCH.captures(&query) {
-            Some(caps) => {
-                let cap = &caps["instance"].to_lowercase();
-                modified_query = modified_query.replace(cap, "")
-                    .replace("instance:", "");
-                Some(if cap.starts_with("https://") {
-                    cap.to_string()
-                } else {
-                    format!("https://{}/", cap)
-                })
-            },
-            None => None
-        };
-        let community = match COMMUNITY_MATCH.captures(&query) {
-            Some(caps) => {
-                let cap = &caps["community"].to_lowercase();
-                modified_query = modified_query.replace(cap, "")
-                    .replace("community:", "");
-
-                // Change the format from the user format of !name@instance
-                // to match the actor_id format of a URL https://instance/c/name.
-                match COMMUNITY_FORMAT.captures(&cap) {
-                    Some(caps2) => {
-                        let name = caps2["name"].to_lowercase();
-                        let instance = caps2["instance"].to_lowercase();
-                        Some(format!("https://{}/c/{}", instance, name))
-                    },
-                    None => None
-                }
-            },
-            None => None
-        };
-        let author = match AUTHOR_MATCH.captures(&query) {
-            Some(caps) => {
-                let cap = &caps["author"].to_lowercase();
-                modified_query = modified_query.replace(cap, "")
-                    .replace("author:", "");
-                
-                // Change the format from the user format of @name@instance
-                // to match the actor_id format of a URL https://instance/c/name.
-                match AUTHOR_FORMAT.captures(&cap) {
-                    Some(caps2) => {
-                        let name = caps2["name"].to_lowercase();
-                        let instance = caps2["instance"].to_lowercase();
-                        Some(format!("https://{}/u/{}", instance, name))
-                    },
-                    None => None
-                }
-            },
-            None => None
-        };
+        let instance = Self::extract_instance(&query, &mut modified_query);
+        let community = Self::extract_community(&query, &mut modified_query);
+        let author = Self::extract_author(&query, &mut modified_query);
+
+        // Languages can come from either the query or the dedicated parameter.
+        let mut languages = search_query.languages.iter().flat_map(|languages| {
+            languages.split(',')
+        }).chain(search_query.lang.as_deref()).chain(LANGUAGE_MATCH.captures_iter(&query).map(|caps| {
+            caps.name("language").unwrap().as_str()
+        })).map(|language| {
+            language.trim().to_lowercase()
+        }).filter(|language| {
+            !language.is_empty()
+        }).collect::<Vec<_>>();
+        languages.sort();
+        languages.dedup();
+        modified_query = LANGUAGE_MATCH.replace_all(&modified_query, " ")
+            .to_string();
+
+        let site = SITE_MATCH.captures(&query).and_then(|caps| {
+            get_domain(&format!("https://{}", &caps["site"]))
+        });
+        modified_query = SITE_MATCH.replace_all(&modified_query, " ")
+            .to_string();
+        let url = URL_MATCH.captures(&query).and_then(|caps| {
+            normalize_url(&caps["url"])
+        });
+        modified_query = URL_MATCH.replace_all(&modified_query, " ")
+            .to_string();
+
+        // Dates are taken to be the very start of that day in UTC.
+        let after = AFTER_MATCH.captures(&query).and_then(|caps| {
+            Self::parse_date(&caps["date"])
+        });
+        modified_query = AFTER_MATCH.replace_all(&modified_query, " ")
+            .to_string();
+        let before = BEFORE_MATCH.captures(&query).and_then(|caps| {
+            Self::parse_date(&caps["date"])
+        });
+        modified_query = BEFORE_MATCH.replace_all(&modified_query, " ")
+            .to_string();
+
+        let sort = search_query.sort.unwrap_or_default();
+        if cursor.as_ref().is_some_and(|cursor| cursor.sort != sort) {
+            return Err(actix_web::error::ErrorBadRequest("The cursor is for a different sort."));
+        }
 
         // normalize the query string to lowercase.
         modified_query = modified_query.to_lowercase()
@@ -223,53 +310,146 @@ impl SearchHandler {
             },
             None => {}
         }
-
-        // tokenize the search query, remove any non-alphanumeric characters from the string
-        // and remove any words that are less than 3 characters long.
-        let query_terms = modified_query.replace(|c : char| {
-            !c.is_alphanumeric() && !c.is_whitespace()
-        }, " ")
-            .split_whitespace()
-            .map(|word| {
-                word.trim().to_string()
-            }).filter(|word| {
-                word.len() > 2
-            }).collect::<HashSet<String>>();
+        if !languages.is_empty() {
+            println!("\tLanguages: '{}'", languages.join(", "));
+        }
+        if let Some(value) = &site {
+            println!("\tSite: '{}'", value);
+        }
+        if let Some(value) = &url {
+            println!("\tUrl: '{}'", value);
+        }
+        if let Some(value) = &after {
+            println!("\tAfter: '{}'", value);
+        }
+        if let Some(value) = &before {
+            println!("\tBefore: '{}'", value);
+        }
+        println!("\tSort: '{:?}'", sort);
 
         // The preferred instance is sent without the https://, re-add it back.
         let preferred_instance_actor_id = format!("https://{}/", search_query.preferred_instance);
 
+        // tokenize the search query and expand each word out to all of the forms
+        // that it could have been stored in the index as.  Without any languages
+        // to search in, the query is taken to be in the language that it appears
+        // to be written in, or else the preferred instance's usual language.
+        let stemming_languages = if languages.is_empty() {
+            let language = match detect_query_language(&modified_query) {
+                Some(language) => Some(language),
+                None => SiteDBO::new(pool.clone())
+                    .get_language(&preferred_instance_actor_id)
+                    .await
+                    .log_error("Error while looking up the preferred instance's language.", true)
+                    .ok()
+                    .flatten()
+                    .and_then(|language| {
+                        SupportedLanguage::from_code(&language)
+                    })
+            };
+            language.into_iter().collect::<Vec<_>>()
+        } else {
+            languages.iter().filter_map(|language| {
+                SupportedLanguage::from_code(language)
+            }).collect::<Vec<_>>()
+        };
+        let mut query_terms = analyze_query(&modified_query, &stemming_languages);
+
         let page = search_query.page.unwrap_or(1).max(1);
+        let page_size = config.search.get_page_size(search_query.page_size);
+
+        let search = SearchDatabase::new(pool);
+
+        // Misspelled words won't match anything, so look for similar words that
+        // were indexed instead.
+        let words_to_correct = get_words_to_correct(&query_terms);
+        let similar_words = if words_to_correct.is_empty() {
+            Vec::new()
+        } else {
+            search.get_similar_words(words_to_correct, Self::SIMILAR_WORD_LIMIT)
+                .await
+                .log_error("Error while finding similar words.", true)
+                .unwrap_or_default()
+        };
+        let corrections = get_corrections(&query_terms, &similar_words);
+        let expanded = expand_query_terms(&mut query_terms, &similar_words);
 
-        let search = SearchDatabase::new(pool.lock().unwrap().clone());
         let search_results = search.search(
             &query_terms, 
-            &instance, 
-            &community, 
-            &author, 
-            &preferred_instance_actor_id,
-            page
+            &SearchFilters {
+                instance,
+                community,
+                author,
+                languages,
+                site,
+                url,
+                after,
+                before,
+                posts : search_query.posts.clone()
+            },
+            &SearchOptions {
+                sort,
+                page,
+                page_size,
+                cursor,
+                facet_limit : search_query.facets.map(|facets| {
+                    facets.clamp(1, Self::MAX_FACETS)
+                }),
+                collapse : search_query.collapse.unwrap_or(true)
+            },
+            &preferred_instance_actor_id
         ).await
             .log_error("Error during search.", true)
             .map_err(|err| {
                 actix_web::error::ErrorInternalServerError(err)
             })?;
 
+        let mut posts = search_results.0;
+        Self::add_snippets(&mut posts, &query_terms);
+
         let len = search_results.1;
-        let total_pages = (len as f32 / Self::PAGE_LIMIT as f32).ceil() as i32;
+        let did_you_mean = if expanded || len < Self::FEW_RESULTS {
+            get_did_you_mean(&query, &corrections)
+        } else {
+            None
+        };
+        let total_pages = (len as f32 / page_size as f32).ceil() as i32;
 
         // Capture the duration that the search took so we can report it back
         // to the user.
         let duration = start.elapsed();
 
         let results: SearchResult = SearchResult {
-            original_query_terms : query_terms,
-            posts : search_results.0,
+            original_query_terms : query_terms.into_iter().map(|query_term| {
+                query_term.term
+            }).collect(),
+            posts,
+            next : search_results.2.as_ref().map(Self::encode_cursor),
+            facets : search_results.3,
+            did_you_mean,
             total_results : len,
             total_pages : total_pages,
             time_taken: duration
         };
 
+        Ok(results)
+    }
+
+    /**
+     * Searches for communities themselves, rather than the posts within them.
+     * Communities can be limited to a single instance with `instance:`, and those
+     * marked as NSFW are left out unless asked for.
+     */
+    pub async fn search_communities<'a>(
+        pool : Data<Mutex<DatabasePool>>,
+        config : Data<ConfigHandle>,
+        community_query : Query<CommunitySearchQuery>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let results = Self::query_communities(pool, &config.get(), &community_query)
+            .await?;
+
         Ok(
             Json(results)
                 .customize()
@@ -277,6 +457,304 @@ impl SearchHandler {
         )
     }
 
+    /**
+     * Runs the community search described by the query.
+     */
+    pub async fn query_communities(
+        pool : DatabasePool,
+        config : &Config,
+        community_query : &CommunitySearchQuery
+    ) -> Result<CommunitySearchResult> {
+        let start = Instant::now();
+
+        // The filters expect to be preceded by a space.
+        let query = format!(" {}", community_query.query);
+        let mut modified_query = query.clone();
+
+        let instance = Self::extract_instance(&query, &mut modified_query);
+
+        modified_query = modified_query.to_lowercase()
+            .trim()
+            .to_string();
+
+        println!("Searching communities for '{}'", modified_query);
+        if let Some(value) = &instance {
+            println!("\tInstance: '{}'", value);
+        }
+
+        let query_terms = analyze_query(&modified_query, &[]);
+
+        // The preferred instance is sent without the https://, re-add it back.
+        let preferred_instance_actor_id = format!("https://{}/", community_query.preferred_instance);
+
+        let page = community_query.page.unwrap_or(1).max(1);
+        let page_size = config.search.get_page_size(community_query.page_size);
+
+
+        let (mut communities, total_results) = SearchDatabase::new(pool)
+            .search_communities(
+                &query_terms, 
+                instance, 
+                community_query.nsfw.unwrap_or(false), 
+                page,
+                page_size
+            ).await
+            .log_error("Error during community search.", true)
+            .map_err(|err| {
+                actix_web::error::ErrorInternalServerError(err)
+            })?;
+
+        for result in &mut communities {
+            result.url = Self::get_preferred_url(
+                &result.community.actor_id, 
+                "c", 
+                &result.community.name, 
+                &preferred_instance_actor_id
+            );
+            if let Some(description) = result.description.take() {
+                let (snippet, highlights) = build_snippet(&description, &query_terms, None);
+                result.snippet = Some(snippet);
+                result.highlights = highlights;
+            }
+        }
+
+        let total_pages = (total_results as f32 / page_size as f32).ceil() as i32;
+
+        let results = CommunitySearchResult {
+            total_results,
+            total_pages,
+            time_taken : start.elapsed(),
+            communities
+        };
+
+        Ok(results)
+    }
+
+    /**
+     * Searches for people by their name, display name and bio, across every
+     * instance.  A handle in the form of @name@instance finds that user first, and
+     * `instance:` limits the results to a single instance.
+     */
+    pub async fn search_authors<'a>(
+        pool : Data<Mutex<DatabasePool>>,
+        config : Data<ConfigHandle>,
+        author_query : Query<AuthorSearchQuery>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let results = Self::query_authors(pool, &config.get(), &author_query)
+            .await?;
+
+        Ok(
+            Json(results)
+                .customize()
+                .insert_header(("cache-control", "public, max-age=86400"))
+        )
+    }
+
+    /**
+     * Runs the author search described by the query.
+     */
+    pub async fn query_authors(
+        pool : DatabasePool,
+        config : &Config,
+        author_query : &AuthorSearchQuery
+    ) -> Result<AuthorSearchResult> {
+        let start = Instant::now();
+
+        // The filters expect to be preceded by a space.
+        let query = format!(" {}", author_query.query);
+        let mut modified_query = query.clone();
+
+        let mut instance = Self::extract_instance(&query, &mut modified_query);
+
+        // Search for the name from the handle, limited to the handle's instance.
+        let handle = AUTHOR_FORMAT.captures(&modified_query).map(|caps| {
+            (caps[0].to_string(), caps["name"].to_string(), caps["instance"].to_lowercase())
+        });
+        if let Some((handle, name, handle_instance)) = handle {
+            instance = Some(format!("https://{}/", handle_instance));
+            modified_query = modified_query.replace(&handle, &name);
+        }
+
+        modified_query = modified_query.to_lowercase()
+            .trim()
+            .to_string();
+
+        println!("Searching authors for '{}'", modified_query);
+        if let Some(value) = &instance {
+            println!("\tInstance: '{}'", value);
+        }
+
+        let query_terms = analyze_query(&modified_query, &[]);
+
+        // The preferred instance is sent without the https://, re-add it back.
+        let preferred_instance_actor_id = format!("https://{}/", author_query.preferred_instance);
+
+        let page = author_query.page.unwrap_or(1).max(1);
+        let page_size = config.search.get_page_size(author_query.page_size);
+
+
+        let (mut authors, total_results) = SearchDatabase::new(pool)
+            .search_authors(
+                &query_terms, 
+                instance, 
+                page,
+                page_size
+            ).await
+            .log_error("Error during author search.", true)
+            .map_err(|err| {
+                actix_web::error::ErrorInternalServerError(err)
+            })?;
+
+        for result in &mut authors {
+            result.url = Self::get_preferred_url(
+                &result.author.actor_id, 
+                "u", 
+                &result.author.name, 
+                &preferred_instance_actor_id
+            );
+            if let Some(bio) = result.bio.take() {
+                let (snippet, highlights) = build_snippet(&bio, &query_terms, None);
+                result.snippet = Some(snippet);
+                result.highlights = highlights;
+            }
+        }
+
+        let total_pages = (total_results as f32 / page_size as f32).ceil() as i32;
+
+        let results = AuthorSearchResult {
+            total_results,
+            total_pages,
+            time_taken : start.elapsed(),
+            authors
+        };
+
+        Ok(results)
+    }
+
+    /**
+     * Turns the cursor into an opaque token that can be passed back to continue
+     * from where the page left off.
+     */
+    fn encode_cursor(
+        cursor : &SearchCursor
+    ) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(cursor).unwrap_or_default())
+    }
+
+    fn decode_cursor(
+        token : &str
+    ) -> Option<SearchCursor> {
+        let json = URL_SAFE_NO_PAD.decode(token).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+
+    /**
+     * Removes the `instance:` filter from the query, returning the actor id of the
+     * instance that it refers to.
+     */
+    fn extract_instance(
+        query : &str,
+        modified_query : &mut String
+    ) -> Option<String> {
+        let caps = INSTANCE_MATCH.captures(query)?;
+        let cap = &caps["instance"].to_lowercase();
+        *modified_query = modified_query.replace(cap, "")
+            .replace("instance:", "");
+        Some(if cap.starts_with("https://") {
+            cap.to_string()
+        } else {
+            format!("https://{}/", cap)
+        })
+    }
+
+    fn extract_community(
+        query : &str,
+        modified_query : &mut String
+    ) -> Option<String> {
+        let caps = COMMUNITY_MATCH.captures(query)?;
+        let cap = &caps["community"].to_lowercase();
+        *modified_query = modified_query.replace(cap, "")
+            .replace("community:", "");
+
+        // Change the format from the user format of !name@instance
+        // to match the actor_id format of a URL https://instance/c/name.
+        let caps = COMMUNITY_FORMAT.captures(cap)?;
+        Some(format!("https://{}/c/{}", &caps["instance"], &caps["name"]))
+    }
+
+    fn extract_author(
+        query : &str,
+        modified_query : &mut String
+    ) -> Option<String> {
+        let caps = AUTHOR_MATCH.captures(query)?;
+        let cap = &caps["author"].to_lowercase();
+        *modified_query = modified_query.replace(cap, "")
+            .replace("author:", "");
+
+        // Change the format from the user format of @name@instance
+        // to match the actor_id format of a URL https://instance/u/name.
+        let caps = AUTHOR_FORMAT.captures(cap)?;
+        Some(format!("https://{}/u/{}", &caps["instance"], &caps["name"]))
+    }
+
+    /**
+     * Links to a community or user through the preferred instance, where the user
+     * should already be logged in, in the same way that the UI links to them from
+     * each post.  `kind` is either 'c' for communities or 'u' for users.
+     */
+    pub fn get_preferred_url(
+        actor_id : &str,
+        kind : &str,
+        name : &str,
+        preferred_instance_actor_id : &str
+    ) -> String {
+        if actor_id.starts_with(preferred_instance_actor_id) {
+            return actor_id.to_string();
+        }
+        match Url::parse(actor_id).ok().and_then(|url| {
+            url.host_str().map(|host| {
+                host.to_string()
+            })
+        }) {
+            Some(host) => format!("{}{}/{}@{}", preferred_instance_actor_id, kind, name, host),
+            None => actor_id.to_string()
+        }
+    }
+
+    /**
+     * Replaces each post's body with a short snippet around the parts of it that
+     * matched the query.
+     */
+    fn add_snippets(
+        posts : &mut [SearchPost],
+        query_terms : &[QueryTerm]
+    ) {
+        for post in posts {
+            let language = post.language.as_deref()
+                .or(post.detected_language.as_deref())
+                .and_then(SupportedLanguage::from_code);
+
+            if let Some(body) = post.body.take() {
+                let (snippet, highlights) = build_snippet(&body, query_terms, language);
+                post.snippet = Some(snippet);
+                post.highlights = highlights;
+            }
+        }
+    }
+
+    fn parse_date(
+        date : &str
+    ) -> Option<DateTime<Utc>> {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)
+            .map(|date| {
+                date.and_utc()
+            })
+    }
+
     /**
      * Returns a list of all available instances that this search engine has seen.
      * 
@@ -300,4 +778,242 @@ impl SearchHandler {
                 .insert_header(("cache-control", "public, max-age=86400"))
         )
     }
+
+    /**
+     * Returns every language that at least one indexed post is written in.
+     */
+    pub async fn get_languages<'a>(
+        pool : Data<Mutex<DatabasePool>>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let languages = LanguageDBO::new(pool)
+            .retrieve_all()
+            .await.map_err(|err| {
+                actix_web::error::ErrorInternalServerError(err)
+            })?;
+
+        Ok(
+            Json(languages)
+                .customize()
+                .insert_header(("cache-control", "public, max-age=86400"))
+        )
+    }
+
+    /**
+     * Lists every community that has a post linking to the given url, along with
+     * those posts.  Used to find all of the threads discussing a single article.
+     */
+    pub async fn get_discussions<'a>(
+        pool : Data<Mutex<DatabasePool>>,
+        discussion_query : Query<DiscussionQuery>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let discussions = Self::query_discussions(pool, &discussion_query)
+            .await?;
+
+        Ok(
+            Json(discussions)
+                .customize()
+                .insert_header(("cache-control", "public, max-age=3600"))
+        )
+    }
+
+    /**
+     * Finds the discussions of the url in the query.
+     */
+    pub async fn query_discussions(
+        pool : DatabasePool,
+        discussion_query : &DiscussionQuery
+    ) -> Result<Vec<SearchDiscussion>> {
+        let normalized_url = normalize_url(&discussion_query.url)
+            .ok_or_else(|| {
+                actix_web::error::ErrorBadRequest("Invalid url.")
+            })?;
+
+        // The preferred instance is sent without the https://, re-add it back.
+        let preferred_instance_actor_id = format!("https://{}/", discussion_query.preferred_instance);
+
+        let mut discussions = SearchDatabase::new(pool)
+            .get_discussions(&normalized_url, &preferred_instance_actor_id)
+            .await
+            .log_error("Error while finding discussions.", true)
+            .map_err(|err| {
+                actix_web::error::ErrorInternalServerError(err)
+            })?;
+
+        for discussion in &mut discussions {
+            Self::add_snippets(&mut discussion.posts, &[]);
+        }
+
+        Ok(discussions)
+    }
+
+    /**
+     * Suggests completions for the last term in the query that the user is still
+     * typing.  Words are taken from the index, and ranked by how many posts they
+     * appear in, while `community:!` and `author:@` complete to the handles of
+     * known communities and authors.
+     */
+    pub async fn suggest<'a>(
+        pool : Data<Mutex<DatabasePool>>,
+        suggest_query : Query<SuggestQuery>
+    ) -> Result<impl Responder> {
+        let pool = pool.lock().unwrap().clone();
+
+        let suggestions = Self::query_suggestions(pool, &suggest_query)
+            .await?;
+
+        Ok(
+            Json(suggestions)
+                .customize()
+                .insert_header(("cache-control", "public, max-age=3600"))
+        )
+    }
+
+    /**
+     * Finds the completions for the prefix in the query.
+     */
+    pub async fn query_suggestions(
+        pool : DatabasePool,
+        suggest_query : &SuggestQuery
+    ) -> Result<Vec<SearchSuggestion>> {
+        let prefix = &suggest_query.prefix;
+
+        // Only the last term is completed, everything before it is kept as-is.
+        let start = prefix.rfind(char::is_whitespace)
+            .map(|index| {
+                index + 1
+            })
+            .unwrap_or(0);
+        let (head, last) = prefix.split_at(start);
+        let last = last.to_lowercase();
+
+        let search = SearchDatabase::new(pool);
+        let suggestions = if let Some(partial) = last.strip_prefix("community:!") {
+            search.suggest_communities(partial, Self::SUGGESTION_LIMIT)
+                .await
+                .map(|suggestions| {
+                    (String::from("community:"), suggestions)
+                })
+        } else if let Some(partial) = last.strip_prefix("author:@") {
+            search.suggest_authors(partial, Self::SUGGESTION_LIMIT)
+                .await
+                .map(|suggestions| {
+                    (String::from("author:"), suggestions)
+                })
+        } else {
+            // Keep any field prefix, such as title:, in front of the completion.
+            let (field, partial) = match last.split_once(':') {
+                Some((field, partial)) if IndexField::from_prefix(field).is_some() => {
+                    (format!("{}:", field), partial)
+                },
+                _ => (String::new(), last.as_str())
+            };
+            // Single letters match far too many words to be useful.
+            if partial.chars().count() < 2 || !partial.chars().all(char::is_alphanumeric) {
+                Ok((field, Vec::new()))
+            } else {
+                search.suggest_words(partial, Self::SUGGESTION_LIMIT)
+                    .await
+                    .map(|suggestions| {
+                        (field, suggestions)
+                    })
+            }
+        }.log_error("Error while finding suggestions.", true)
+            .map_err(|err| {
+                actix_web::error::ErrorInternalServerError(err)
+            })?;
+
+        let (field, mut suggestions) = suggestions;
+        for suggestion in &mut suggestions {
+            suggestion.term = format!("{}{}", field, suggestion.term);
+            suggestion.text = format!("{}{}", head, suggestion.term);
+        }
+
+        Ok(suggestions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{
+        engine::general_purpose::URL_SAFE_NO_PAD,
+        Engine
+    };
+    use crate::{
+        api::search::models::search::SearchSort,
+        database::dbo::search::SearchCursor
+    };
+    use super::SearchHandler;
+
+    #[test]
+    fn extracts_filters_at_the_start_of_the_query() {
+        let query = "community:!rust@lemmy.ml release";
+        let mut modified_query = query.to_string();
+        assert_eq!(
+            SearchHandler::extract_community(query, &mut modified_query),
+            Some("https://lemmy.ml/c/rust".to_string())
+        );
+        assert_eq!(modified_query.trim(), "release");
+
+        let query = "author:@Alice@lemmy.ml release";
+        let mut modified_query = query.to_string();
+        assert_eq!(
+            SearchHandler::extract_author(query, &mut modified_query),
+            Some("https://lemmy.ml/u/alice".to_string())
+        );
+
+        let query = "instance:lemmy.world release";
+        let mut modified_query = query.to_string();
+        assert_eq!(
+            SearchHandler::extract_instance(query, &mut modified_query),
+            Some("https://lemmy.world/".to_string())
+        );
+        assert_eq!(modified_query.trim(), "release");
+    }
+
+    #[test]
+    fn extracts_filters_after_other_words() {
+        let query = "release community:!rust@lemmy.ml";
+        let mut modified_query = query.to_string();
+        assert_eq!(
+            SearchHandler::extract_community(query, &mut modified_query),
+            Some("https://lemmy.ml/c/rust".to_string())
+        );
+        assert_eq!(modified_query.trim(), "release");
+    }
+
+    #[test]
+    fn ignores_filters_within_a_word() {
+        let query = "xcommunity:!rust@lemmy.ml";
+        let mut modified_query = query.to_string();
+        assert_eq!(SearchHandler::extract_community(query, &mut modified_query), None);
+        assert_eq!(modified_query, query);
+    }
+
+    #[test]
+    fn decodes_an_encoded_cursor() {
+        let cursor = SearchCursor {
+            sort : SearchSort::New,
+            total_results : 120,
+            sort_key : 1689415200000000,
+            matches : 2,
+            relevance : 16,
+            score : -3,
+            ap_id : "https://lemmy.ml/post/1".to_string()
+        };
+
+        let token = SearchHandler::encode_cursor(&cursor);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(SearchHandler::decode_cursor(&token), Some(cursor));
+    }
+
+    #[test]
+    fn rejects_invalid_cursors() {
+        assert_eq!(SearchHandler::decode_cursor(""), None);
+        assert_eq!(SearchHandler::decode_cursor("not a cursor!"), None);
+        assert_eq!(SearchHandler::decode_cursor(&URL_SAFE_NO_PAD.encode("{\"sort\":\"new\"}")), None);
+    }
 }
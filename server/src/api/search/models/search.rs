@@ -1,5 +1,9 @@
 use std::{time::Duration, collections::HashSet};
 
+use chrono::{
+    DateTime, 
+    Utc
+};
 use serde::{
     Serialize, 
     Deserialize
@@ -14,7 +18,41 @@ pub struct Version {
 pub struct SearchQuery {
     pub query : String,
     pub preferred_instance : String,
-    pub page : Option<i32>
+    /// A comma separated list of ISO 639-1 language codes to limit results to.
+    pub languages : Option<String>,
+    /// A single ISO 639-1 language code, combined with `languages`.
+    pub lang : Option<String>,
+    pub sort : Option<SearchSort>,
+    /// How many of the most common values to count for each facet, if any.
+    pub facets : Option<i64>,
+    /// Whether cross-posts and duplicates are grouped into a single result, which
+    /// they are unless this is false.
+    pub collapse : Option<bool>,
+    pub page : Option<i32>,
+    pub page_size : Option<i32>,
+    /// The `next` token from the previous page, which takes the place of `page`.
+    pub cursor : Option<String>,
+    /// Only search these posts.  Never read from the request, this is used to
+    /// check saved searches against the posts that were recently indexed.
+    #[serde(skip)]
+    pub posts : Option<Vec<String>>
+}
+
+/**
+ * How the results of a search should be ordered.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchSort {
+    /// Posts that best match the query first.
+    #[default]
+    Relevance,
+    /// The most recently published posts first.
+    New,
+    /// The posts with the highest score first.
+    Top,
+    /// The posts with the most comments first.
+    Comments
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -23,17 +61,80 @@ pub struct SearchResult {
     pub total_results : i32,
     pub total_pages : i32,
     pub time_taken : Duration,
-    pub posts : Vec<SearchPost>
+    pub posts : Vec<SearchPost>,
+    /// Pass this as the `cursor` to get the next page, if there is one.
+    pub next : Option<String>,
+    pub facets : Option<SearchFacets>,
+    /// The query with any misspelled words corrected, when there were few results.
+    pub did_you_mean : Option<String>
+}
+
+/**
+ * The most common communities, instances, authors and languages among every post
+ * that matched the search.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SearchFacets {
+    pub communities : Vec<SearchFacet>,
+    pub instances : Vec<SearchFacet>,
+    pub authors : Vec<SearchFacet>,
+    pub languages : Vec<SearchFacet>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchFacet {
+    pub name : String,
+    /// What to add to the query to limit the results to just this value.
+    pub filter : String,
+    pub count : i64
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchPost {
     pub url : Option<String>,
     pub name : String,
+    /// The full body is only used to build the snippet, and is never sent.
+    #[serde(skip)]
     pub body : Option<String>,
+    pub snippet : Option<String>,
+    pub highlights : Vec<SearchHighlight>,
+    pub language : Option<String>,
+    pub detected_language : Option<String>,
+    pub language_confidence : Option<f32>,
+    pub published : Option<DateTime<Utc>>,
+    pub comments : Option<i64>,
     pub remote_id : i64,
     pub author : SearchAuthor,
     pub community: SearchCommunity,
+    /// The other posts of the same link, or with the same content, that were
+    /// collapsed into this one.
+    pub also_posted_in : Vec<SearchCrossPost>,
+    /// The rest are only needed to rebuild the post for the Lemmy compatible API,
+    /// and are only read by a search.
+    #[serde(skip)]
+    pub ap_id : String,
+    #[serde(skip)]
+    pub score : Option<i64>,
+    /// The ids of the community and author on the preferred instance, if known.
+    #[serde(skip)]
+    pub community_remote_id : Option<i64>,
+    #[serde(skip)]
+    pub author_remote_id : Option<i64>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchCrossPost {
+    pub remote_id : i64,
+    pub community : SearchCommunity
+}
+
+/**
+ * Where a match starts and ends in a snippet, counted in characters.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchHighlight {
+    pub start : usize,
+    pub end : usize
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -52,6 +153,109 @@ pub struct SearchCommunity {
     pub title : Option<String>
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DiscussionQuery {
+    pub url : String,
+    pub preferred_instance : String
+}
+
+/**
+ * All of the posts in a single community that link to the same page.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchDiscussion {
+    pub community : SearchCommunity,
+    pub posts : Vec<SearchPost>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CommunitySearchQuery {
+    pub query : String,
+    pub preferred_instance : String,
+    /// Whether to include communities that are marked as NSFW.
+    pub nsfw : Option<bool>,
+    pub page : Option<i32>,
+    pub page_size : Option<i32>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CommunitySearchResult {
+    pub total_results : i32,
+    pub total_pages : i32,
+    pub time_taken : Duration,
+    pub communities : Vec<SearchCommunityResult>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchCommunityResult {
+    pub community : SearchCommunity,
+    /// Where to view the community on the preferred instance.
+    pub url : String,
+    /// The full description is only used to build the snippet, and is never sent.
+    #[serde(skip)]
+    pub description : Option<String>,
+    pub snippet : Option<String>,
+    pub highlights : Vec<SearchHighlight>,
+    pub nsfw : bool,
+    pub subscribers : Option<i64>,
+    pub active_users_month : Option<i64>,
+    /// How many of the community's posts have been indexed.
+    pub posts : i64
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AuthorSearchQuery {
+    pub query : String,
+    pub preferred_instance : String,
+    pub page : Option<i32>,
+    pub page_size : Option<i32>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AuthorSearchResult {
+    pub total_results : i32,
+    pub total_pages : i32,
+    pub time_taken : Duration,
+    pub authors : Vec<SearchAuthorResult>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchAuthorResult {
+    pub author : SearchAuthor,
+    /// Where to view the user's profile on the preferred instance.
+    pub url : String,
+    /// The full bio is only used to build the snippet, and is never sent.
+    #[serde(skip)]
+    pub bio : Option<String>,
+    pub snippet : Option<String>,
+    pub highlights : Vec<SearchHighlight>,
+    /// How many of the user's posts have been indexed.
+    pub posts : i64
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SuggestQuery {
+    pub prefix : String
+}
+
+/**
+ * A possible completion of the last term that the user is typing.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchSuggestion {
+    /// The whole query, with the last term replaced by the completion.
+    pub text : String,
+    pub term : String,
+    /// How many posts the completed term appears in.
+    pub count : i64
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SearchLanguage {
+    pub code : String,
+    pub name : String
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchInstance {
     pub actor_id : String,
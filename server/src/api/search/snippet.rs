@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+use crate::{
+    api::search::models::search::SearchHighlight,
+    crawler::analyzer::{
+        extract_markdown,
+        IndexField,
+        QueryTerm,
+        SupportedLanguage
+    }
+};
+
+/**
+ * The most characters that a snippet will contain, not including the ellipses.
+ */
+const SNIPPET_LENGTH : usize = 240;
+
+/**
+ * How many characters to show before the first match, so that it has some context.
+ */
+const SNIPPET_CONTEXT : usize = 60;
+
+const ELLIPSIS : char = '…';
+
+/**
+ * Builds a short excerpt of the post's body centered around the first word that
+ * matches the query, along with the character offsets of every matching word in
+ * that excerpt.  Only the text that a reader would see is used, so Markdown, link
+ * targets and code are left out.
+ */
+pub fn build_snippet(
+    body : &str,
+    query : &[QueryTerm],
+    language : Option<SupportedLanguage>
+) -> (String, Vec<SearchHighlight>) {
+    let text = extract_markdown(body).text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect::<Vec<_>>();
+
+    // Terms that were limited to the title can't match anything in the body.
+    let body_fields = IndexField::Body.get_flag() | IndexField::Code.get_flag();
+    let variants = query.iter()
+        .filter(|query_term| {
+            query_term.fields & body_fields != 0
+        })
+        .flat_map(|query_term| {
+            query_term.variants.iter().cloned()
+        })
+        .collect::<HashSet<_>>();
+
+    let matches = find_words(&text).into_iter().filter(|(start, end)| {
+        let word = text[*start..*end].iter()
+            .collect::<String>()
+            .to_lowercase();
+        variants.contains(&word) || language.map(|language| {
+            variants.contains(&language.stem(&word))
+        }).unwrap_or(false)
+    }).collect::<Vec<_>>();
+
+    let (start, end) = get_window(&text, matches.first().map(|(start, _)| *start));
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push(ELLIPSIS);
+    }
+    let offset = snippet.chars().count();
+    snippet.extend(&text[start..end]);
+    if end < text.len() {
+        snippet.push(ELLIPSIS);
+    }
+
+    let highlights = matches.into_iter().filter(|(match_start, match_end)| {
+        *match_start >= start && *match_end <= end
+    }).map(|(match_start, match_end)| {
+        SearchHighlight {
+            start : match_start - start + offset,
+            end : match_end - start + offset
+        }
+    }).collect();
+
+    (snippet, highlights)
+}
+
+/**
+ * Finds the start and end of every word in the text, splitting words the same way
+ * that the analyzer does.
+ */
+fn find_words(
+    text : &[char]
+) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in text.iter().enumerate() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(word_start)) => {
+                words.push((word_start, index));
+                start = None;
+            },
+            _ => {}
+        }
+    }
+    if let Some(word_start) = start {
+        words.push((word_start, text.len()));
+    }
+    words
+}
+
+/**
+ * Picks which part of the text to show, trying to not cut any words in half.
+ */
+fn get_window(
+    text : &[char],
+    first_match : Option<usize>
+) -> (usize, usize) {
+    if text.len() <= SNIPPET_LENGTH {
+        return (0, text.len());
+    }
+
+    let mut start = first_match.unwrap_or(0)
+        .saturating_sub(SNIPPET_CONTEXT)
+        .min(text.len() - SNIPPET_LENGTH);
+    if start > 0 {
+        start = text[start..].iter()
+            .position(|c| c.is_whitespace())
+            .map(|position| start + position + 1)
+            .filter(|position| first_match.is_none_or(|first_match| *position <= first_match))
+            .unwrap_or(start);
+    }
+
+    let mut end = (start + SNIPPET_LENGTH).min(text.len());
+    if end < text.len() {
+        end = text[start..end].iter()
+            .rposition(|c| c.is_whitespace())
+            .map(|position| start + position)
+            .filter(|position| *position > start)
+            .unwrap_or(end);
+    }
+
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::crawler::analyzer::{
+        tests::query_term,
+        IndexField,
+        QueryTerm,
+        SupportedLanguage
+    };
+    use super::{
+        build_snippet,
+        ELLIPSIS,
+        SNIPPET_LENGTH
+    };
+
+    fn highlighted(
+        snippet : &str,
+        start : usize,
+        end : usize
+    ) -> String {
+        snippet.chars().skip(start).take(end - start).collect()
+    }
+
+    #[test]
+    fn highlights_every_match_in_a_short_body() {
+        let query = [query_term("rust", IndexField::get_all_flags())];
+        let (snippet, highlights) = build_snippet("I like **Rust**, rust is great.", &query, None);
+
+        assert_eq!(snippet, "I like Rust, rust is great.");
+        assert_eq!(highlights.len(), 2);
+        assert_eq!(highlighted(&snippet, highlights[0].start, highlights[0].end), "Rust");
+        assert_eq!(highlighted(&snippet, highlights[1].start, highlights[1].end), "rust");
+    }
+
+    #[test]
+    fn centers_a_long_body_around_the_first_match() {
+        let body = format!("{} Rust {}", "word ".repeat(100), "word ".repeat(100));
+        let query = [query_term("rust", IndexField::get_all_flags())];
+        let (snippet, highlights) = build_snippet(&body, &query, None);
+
+        assert!(snippet.starts_with(ELLIPSIS));
+        assert!(snippet.ends_with(ELLIPSIS));
+        assert!(snippet.chars().count() <= SNIPPET_LENGTH + 2);
+        assert_eq!(highlights.len(), 1);
+        assert_eq!(highlighted(&snippet, highlights[0].start, highlights[0].end), "Rust");
+    }
+
+    #[test]
+    fn counts_offsets_in_characters() {
+        let query = [query_term("über", IndexField::get_all_flags())];
+        let (snippet, highlights) = build_snippet("Ärger über alles", &query, None);
+
+        assert_eq!(highlights[0].start, 6);
+        assert_eq!(highlights[0].end, 10);
+        assert_eq!(highlighted(&snippet, highlights[0].start, highlights[0].end), "über");
+    }
+
+    #[test]
+    fn matches_words_by_their_stem() {
+        let query = [QueryTerm {
+            term : "running".to_string(),
+            variants : HashSet::from(["running".to_string(), "run".to_string()]),
+            fields : IndexField::get_all_flags()
+        }];
+        let (snippet, highlights) = build_snippet("She runs every day", &query, Some(SupportedLanguage::English));
+
+        assert_eq!(highlights.len(), 1);
+        assert_eq!(highlighted(&snippet, highlights[0].start, highlights[0].end), "runs");
+    }
+
+    #[test]
+    fn ignores_terms_limited_to_the_title() {
+        let query = [query_term("rust", IndexField::Title.get_flag())];
+        let (_, highlights) = build_snippet("I like Rust", &query, None);
+
+        assert!(highlights.is_empty());
+    }
+}
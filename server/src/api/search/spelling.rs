@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use strsim::osa_distance;
+use crate::{
+    crawler::analyzer::{
+        IndexField,
+        QueryTerm
+    },
+    database::dbo::search::SimilarWord
+};
+
+/**
+ * How many typos a word can have and still be corrected.  Short words are left
+ * alone, as nearly every other short word would be within a typo or two of them.
+ */
+pub fn get_max_edit_distance(
+    word : &str
+) -> usize {
+    match word.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2
+    }
+}
+
+/**
+ * Every form of the query's words that is long enough to look for similar words.
+ */
+pub fn get_words_to_correct(
+    query_terms : &[QueryTerm]
+) -> Vec<String> {
+    let mut words = query_terms.iter()
+        .flat_map(|query_term| {
+            query_term.variants.iter()
+        })
+        .filter(|variant| {
+            get_max_edit_distance(variant) > 0
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    words.sort();
+    words.dedup();
+    words
+}
+
+/**
+ * Adds the similar words to any term that doesn't appear in the index at all, so
+ * that a misspelled word still finds the posts that spelled it correctly.  Returns
+ * whether any of the terms were expanded.
+ */
+pub fn expand_query_terms(
+    query_terms : &mut [QueryTerm],
+    similar_words : &[SimilarWord]
+) -> bool {
+    let mut expanded = false;
+    for query_term in query_terms {
+        if is_indexed(query_term, similar_words) {
+            continue;
+        }
+
+        let words = similar_words.iter()
+            .filter(|similar_word| {
+                is_close_enough(query_term, similar_word)
+            })
+            .map(|similar_word| {
+                similar_word.word.clone()
+            })
+            .collect::<Vec<_>>();
+
+        expanded |= !words.is_empty();
+        query_term.variants.extend(words);
+    }
+    expanded
+}
+
+/**
+ * Picks the best replacement for each term that has a similar word which appears
+ * in more posts than the term itself does.  Words are stored as their stems, so
+ * whatever the stemmer cut off of the user's word is added back on to the end.
+ */
+pub fn get_corrections(
+    query_terms : &[QueryTerm],
+    similar_words : &[SimilarWord]
+) -> HashMap<String, String> {
+    query_terms.iter().filter_map(|query_term| {
+        let posts = similar_words.iter()
+            .filter(|similar_word| {
+                query_term.variants.contains(&similar_word.word)
+            })
+            .map(|similar_word| {
+                similar_word.posts
+            })
+            .max()
+            .unwrap_or(0);
+
+        let best = similar_words.iter()
+            .filter(|similar_word| {
+                similar_word.posts > posts && is_close_enough(query_term, similar_word)
+            })
+            .min_by_key(|similar_word| {
+                (osa_distance(&similar_word.variant, &similar_word.word), -similar_word.posts)
+            })?;
+
+        let suffix = query_term.term.strip_prefix(&best.variant)
+            .unwrap_or("");
+        let correction = format!("{}{}", best.word, suffix);
+
+        if correction == query_term.term {
+            None
+        } else {
+            Some((query_term.term.clone(), correction))
+        }
+    }).collect()
+}
+
+/**
+ * Rewrites the user's query with each of the corrections, leaving any filters
+ * untouched.  Returns nothing if there was nothing to correct.
+ */
+pub fn get_did_you_mean(
+    query : &str,
+    corrections : &HashMap<String, String>
+) -> Option<String> {
+    if corrections.is_empty() {
+        return None;
+    }
+
+    let did_you_mean = query.split(' ')
+        .map(|chunk| {
+            let is_filter = chunk.split_once(':').is_some_and(|(prefix, _)| {
+                IndexField::from_prefix(&prefix.to_lowercase()).is_none()
+            });
+            if is_filter {
+                chunk.to_string()
+            } else {
+                replace_words(chunk, corrections)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if did_you_mean == query {
+        None
+    } else {
+        Some(did_you_mean)
+    }
+}
+
+/**
+ * Whether any form of the term was found in the index.
+ */
+fn is_indexed(
+    query_term : &QueryTerm,
+    similar_words : &[SimilarWord]
+) -> bool {
+    similar_words.iter().any(|similar_word| {
+        similar_word.posts > 0 && query_term.variants.contains(&similar_word.word)
+    })
+}
+
+fn is_close_enough(
+    query_term : &QueryTerm,
+    similar_word : &SimilarWord
+) -> bool {
+    similar_word.posts > 0
+        && query_term.variants.contains(&similar_word.variant)
+        && !query_term.variants.contains(&similar_word.word)
+        && osa_distance(&similar_word.variant, &similar_word.word) <= get_max_edit_distance(&similar_word.variant)
+}
+
+/**
+ * Replaces each word in the text, splitting words the same way that the analyzer
+ * does.
+ */
+fn replace_words(
+    text : &str,
+    corrections : &HashMap<String, String>
+) -> String {
+    let mut result = String::new();
+    let mut word = String::new();
+    for c in text.chars().chain(std::iter::once(' ')) {
+        if c.is_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            match corrections.get(&word.to_lowercase()) {
+                Some(correction) => result.push_str(correction),
+                None => result.push_str(&word)
+            }
+            word.clear();
+        }
+        result.push(c);
+    }
+    result.pop();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::{
+        crawler::analyzer::{
+            tests::query_term,
+            IndexField
+        },
+        database::dbo::search::SimilarWord
+    };
+    use super::{
+        expand_query_terms,
+        get_corrections,
+        get_did_you_mean,
+        get_max_edit_distance
+    };
+
+    fn similar_word(
+        variant : &str,
+        word : &str,
+        posts : i64
+    ) -> SimilarWord {
+        SimilarWord {
+            variant : variant.to_string(),
+            word : word.to_string(),
+            posts
+        }
+    }
+
+    #[test]
+    fn allows_more_typos_in_longer_words() {
+        assert_eq!(get_max_edit_distance("cat"), 0);
+        assert_eq!(get_max_edit_distance("rust"), 1);
+        assert_eq!(get_max_edit_distance("release"), 1);
+        assert_eq!(get_max_edit_distance("releases"), 2);
+        assert_eq!(get_max_edit_distance("ääää"), 1);
+    }
+
+    #[test]
+    fn expands_words_that_arent_indexed() {
+        let mut query_terms = [query_term("rsut", IndexField::get_all_flags())];
+        let similar_words = [similar_word("rsut", "rust", 10), similar_word("rsut", "rut", 5), similar_word("rsut", "roast", 5)];
+
+        assert!(expand_query_terms(&mut query_terms, &similar_words));
+        assert!(query_terms[0].variants.contains("rust"));
+        assert!(query_terms[0].variants.contains("rut"));
+        assert!(!query_terms[0].variants.contains("roast"));
+    }
+
+    #[test]
+    fn leaves_indexed_words_alone() {
+        let mut query_terms = [query_term("rust", IndexField::get_all_flags())];
+        let similar_words = [similar_word("rust", "rust", 10), similar_word("rust", "bust", 20)];
+
+        assert!(!expand_query_terms(&mut query_terms, &similar_words));
+        assert_eq!(query_terms[0].variants.len(), 1);
+    }
+
+    #[test]
+    fn leaves_short_words_alone() {
+        let mut query_terms = [query_term("cat", IndexField::get_all_flags())];
+        let similar_words = [similar_word("cat", "bat", 10)];
+
+        assert!(!expand_query_terms(&mut query_terms, &similar_words));
+    }
+
+    #[test]
+    fn suggests_the_closest_and_most_common_correction() {
+        let query_terms = [query_term("releese", IndexField::get_all_flags())];
+        let similar_words = [similar_word("releese", "release", 10), similar_word("releese", "released", 50)];
+
+        let corrections = get_corrections(&query_terms, &similar_words);
+        assert_eq!(corrections.get("releese").map(String::as_str), Some("release"));
+
+        assert_eq!(
+            get_did_you_mean("Releese community:!rust@lemmy.ml", &corrections).as_deref(),
+            Some("release community:!rust@lemmy.ml")
+        );
+        assert_eq!(get_did_you_mean("rust", &HashMap::new()), None);
+    }
+}
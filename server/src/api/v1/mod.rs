@@ -0,0 +1,343 @@
+pub mod models;
+
+use std::{
+    collections::HashMap,
+    sync::Mutex
+};
+use actix_web::{
+    Responder,
+    Result,
+    Route,
+    web::{
+        Data,
+        Json,
+        Query,
+        get
+    }
+};
+use utoipa::OpenApi;
+use crate::{
+    api::search::SearchHandler,
+    config::handle::ConfigHandle,
+    database::{
+        DatabasePool,
+        dbo::{
+            language::LanguageDBO,
+            site::SiteDBO
+        }
+    }
+};
+use self::models::{
+    Author,
+    AuthorMatch,
+    AuthorSearchParams,
+    AuthorSearchResponse,
+    Community,
+    CommunityMatch,
+    CommunitySearchParams,
+    CommunitySearchResponse,
+    CrossPost,
+    Discussion,
+    DiscussionParams,
+    Facet,
+    Facets,
+    Highlight,
+    Instance,
+    Language,
+    Post,
+    PostSearchParams,
+    PostSearchResponse,
+    Sort,
+    SuggestParams,
+    Suggestion,
+    Version
+};
+
+/**
+ * The versioned API for third-party clients.  Each endpoint reuses the same search
+ * as the UI's, but only ever responds with the models in `models`, which are
+ * described by the OpenAPI document at `/api/v1/openapi.json`.
+ */
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Lemmy Search",
+        description = "Search the posts, communities and users of the Lemmy instances that have been crawled."
+    ),
+    paths(
+        version,
+        get_instances,
+        get_languages,
+        search,
+        search_communities,
+        search_authors,
+        get_discussions,
+        suggest
+    ),
+    components(
+        schemas(
+            Author,
+            AuthorMatch,
+            AuthorSearchResponse,
+            Community,
+            CommunityMatch,
+            CommunitySearchResponse,
+            CrossPost,
+            Discussion,
+            Facet,
+            Facets,
+            Highlight,
+            Instance,
+            Language,
+            Post,
+            PostSearchResponse,
+            Sort,
+            Suggestion,
+            Version
+        )
+    )
+)]
+pub struct ApiDoc;
+
+pub struct ApiV1Handler {
+    pub routes : HashMap<String, Route>
+}
+
+impl ApiV1Handler {
+
+    pub fn new() -> Self {
+        let mut routes = HashMap::<String, Route>::new();
+        routes.insert("/api/v1/openapi.json".to_string(), get().to(get_openapi));
+        routes.insert("/api/v1/version".to_string(), get().to(version));
+        routes.insert("/api/v1/instances".to_string(), get().to(get_instances));
+        routes.insert("/api/v1/languages".to_string(), get().to(get_languages));
+        routes.insert("/api/v1/search".to_string(), get().to(search));
+        routes.insert("/api/v1/search/communities".to_string(), get().to(search_communities));
+        routes.insert("/api/v1/search/authors".to_string(), get().to(search_authors));
+        routes.insert("/api/v1/discussions".to_string(), get().to(get_discussions));
+        routes.insert("/api/v1/suggest".to_string(), get().to(suggest));
+
+        Self {
+            routes
+        }
+    }
+}
+
+pub async fn get_openapi<'a>(
+
+) -> Result<impl Responder> {
+    Ok(
+        Json(ApiDoc::openapi())
+            .customize()
+            .insert_header(("cache-control", "public, max-age=86400"))
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/version",
+    responses(
+        (status = 200, description = "The version of the server.", body = Version)
+    )
+)]
+pub async fn version<'a>(
+
+) -> Result<impl Responder> {
+    Ok(
+        Json(
+            Version {
+                version : env!("CARGO_PKG_VERSION").to_string()
+            }
+        ).customize()
+        .insert_header(("cache-control", "public, max-age=86400"))
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/instances",
+    responses(
+        (status = 200, description = "Every instance whose posts are indexed.", body = [Instance])
+    )
+)]
+pub async fn get_instances<'a>(
+    pool : Data<Mutex<DatabasePool>>
+) -> Result<impl Responder> {
+    let pool = pool.lock().unwrap().clone();
+
+    let instances = SiteDBO::new(pool)
+        .retrieve_all()
+        .await
+        .map_err(|err| {
+            actix_web::error::ErrorInternalServerError(err)
+        })?
+        .into_iter()
+        .map(Instance::from)
+        .collect::<Vec<_>>();
+
+    Ok(
+        Json(instances)
+            .customize()
+            .insert_header(("cache-control", "public, max-age=86400"))
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/languages",
+    responses(
+        (status = 200, description = "Every language that at least one indexed post is written in.", body = [Language])
+    )
+)]
+pub async fn get_languages<'a>(
+    pool : Data<Mutex<DatabasePool>>
+) -> Result<impl Responder> {
+    let pool = pool.lock().unwrap().clone();
+
+    let languages = LanguageDBO::new(pool)
+        .retrieve_all()
+        .await
+        .map_err(|err| {
+            actix_web::error::ErrorInternalServerError(err)
+        })?
+        .into_iter()
+        .map(Language::from)
+        .collect::<Vec<_>>();
+
+    Ok(
+        Json(languages)
+            .customize()
+            .insert_header(("cache-control", "public, max-age=86400"))
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/search",
+    params(PostSearchParams),
+    responses(
+        (status = 200, description = "The posts that match the query.", body = PostSearchResponse),
+        (status = 400, description = "The cursor is invalid, or was for a different sort.")
+    )
+)]
+pub async fn search<'a>(
+    pool : Data<Mutex<DatabasePool>>,
+    config : Data<ConfigHandle>,
+    params : Query<PostSearchParams>
+) -> Result<impl Responder> {
+    let pool = pool.lock().unwrap().clone();
+
+    let results = SearchHandler::query_posts(pool, &config.get(), &params.into_inner().into())
+        .await?;
+
+    Ok(
+        Json(PostSearchResponse::from(results))
+            .customize()
+            .insert_header(("cache-control", "public, max-age=86400"))
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/search/communities",
+    params(CommunitySearchParams),
+    responses(
+        (status = 200, description = "The communities that match the query.", body = CommunitySearchResponse)
+    )
+)]
+pub async fn search_communities<'a>(
+    pool : Data<Mutex<DatabasePool>>,
+    config : Data<ConfigHandle>,
+    params : Query<CommunitySearchParams>
+) -> Result<impl Responder> {
+    let pool = pool.lock().unwrap().clone();
+
+    let results = SearchHandler::query_communities(pool, &config.get(), &params.into_inner().into())
+        .await?;
+
+    Ok(
+        Json(CommunitySearchResponse::from(results))
+            .customize()
+            .insert_header(("cache-control", "public, max-age=86400"))
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/search/authors",
+    params(AuthorSearchParams),
+    responses(
+        (status = 200, description = "The users that match the query.", body = AuthorSearchResponse)
+    )
+)]
+pub async fn search_authors<'a>(
+    pool : Data<Mutex<DatabasePool>>,
+    config : Data<ConfigHandle>,
+    params : Query<AuthorSearchParams>
+) -> Result<impl Responder> {
+    let pool = pool.lock().unwrap().clone();
+
+    let results = SearchHandler::query_authors(pool, &config.get(), &params.into_inner().into())
+        .await?;
+
+    Ok(
+        Json(AuthorSearchResponse::from(results))
+            .customize()
+            .insert_header(("cache-control", "public, max-age=86400"))
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/discussions",
+    params(DiscussionParams),
+    responses(
+        (status = 200, description = "Every community with a post linking to the url, along with those posts.", body = [Discussion]),
+        (status = 400, description = "The url is invalid.")
+    )
+)]
+pub async fn get_discussions<'a>(
+    pool : Data<Mutex<DatabasePool>>,
+    params : Query<DiscussionParams>
+) -> Result<impl Responder> {
+    let pool = pool.lock().unwrap().clone();
+
+    let discussions = SearchHandler::query_discussions(pool, &params.into_inner().into())
+        .await?
+        .into_iter()
+        .map(Discussion::from)
+        .collect::<Vec<_>>();
+
+    Ok(
+        Json(discussions)
+            .customize()
+            .insert_header(("cache-control", "public, max-age=3600"))
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/suggest",
+    params(SuggestParams),
+    responses(
+        (status = 200, description = "Completions of the last term in the prefix.", body = [Suggestion])
+    )
+)]
+pub async fn suggest<'a>(
+    pool : Data<Mutex<DatabasePool>>,
+    params : Query<SuggestParams>
+) -> Result<impl Responder> {
+    let pool = pool.lock().unwrap().clone();
+
+    let suggestions = SearchHandler::query_suggestions(pool, &params.into_inner().into())
+        .await?
+        .into_iter()
+        .map(Suggestion::from)
+        .collect::<Vec<_>>();
+
+    Ok(
+        Json(suggestions)
+            .customize()
+            .insert_header(("cache-control", "public, max-age=3600"))
+    )
+}
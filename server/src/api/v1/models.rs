@@ -0,0 +1,573 @@
+use chrono::{
+    DateTime,
+    Utc
+};
+use serde::{
+    Serialize,
+    Deserialize
+};
+use url::Url;
+use utoipa::{
+    IntoParams,
+    ToSchema
+};
+
+use crate::api::{
+    lemmy::models::site::SiteView,
+    search::models::search::{
+        AuthorSearchQuery,
+        AuthorSearchResult,
+        CommunitySearchQuery,
+        CommunitySearchResult,
+        DiscussionQuery,
+        SearchAuthor,
+        SearchAuthorResult,
+        SearchCommunity,
+        SearchCommunityResult,
+        SearchCrossPost,
+        SearchDiscussion,
+        SearchFacet,
+        SearchFacets,
+        SearchHighlight,
+        SearchLanguage,
+        SearchPost,
+        SearchQuery,
+        SearchResult,
+        SearchSort,
+        SearchSuggestion,
+        SuggestQuery
+    }
+};
+
+/*
+ * Everything in here is the public contract of the `/api/v1/` endpoints.  They're
+ * kept separate from both the crawler's models and the ones used by the UI so that
+ * neither can change what third-party clients see by accident.
+ */
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Version {
+    pub version : String
+}
+
+/**
+ * An instance whose posts are indexed.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Instance {
+    pub name : String,
+    #[schema(example = "https://lemmy.ml/")]
+    pub actor_id : String,
+    #[schema(example = "lemmy.ml")]
+    pub domain : String
+}
+
+impl From<SiteView> for Instance {
+    fn from(value : SiteView) -> Self {
+        let domain = Url::parse(&value.site.actor_id)
+            .ok()
+            .and_then(|url| {
+                url.host_str().map(str::to_string)
+            })
+            .unwrap_or_default();
+
+        Self {
+            name : value.site.name,
+            actor_id : value.site.actor_id,
+            domain
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Language {
+    /// The ISO 639-1 code of the language.
+    #[schema(example = "en")]
+    pub code : String,
+    #[schema(example = "English")]
+    pub name : String
+}
+
+impl From<SearchLanguage> for Language {
+    fn from(value : SearchLanguage) -> Self {
+        Self {
+            code : value.code,
+            name : value.name
+        }
+    }
+}
+
+/**
+ * How the posts that match a search are ordered.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    #[default]
+    Relevance,
+    New,
+    Top,
+    Comments
+}
+
+impl From<Sort> for SearchSort {
+    fn from(value : Sort) -> Self {
+        match value {
+            Sort::Relevance => SearchSort::Relevance,
+            Sort::New => SearchSort::New,
+            Sort::Top => SearchSort::Top,
+            Sort::Comments => SearchSort::Comments
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PostSearchParams {
+    /// The search query, which may include filters such as `community:`, `author:`,
+    /// `instance:`, `lang:`, `after:` and `before:`.
+    #[param(example = "rust release community:!rust@lemmy.ml")]
+    pub query : String,
+    /// The domain of the instance the results are viewed from.  Every `remote_id`
+    /// in the response is the id of the post on this instance.
+    #[param(example = "lemmy.ml")]
+    pub preferred_instance : String,
+    /// A comma separated list of ISO 639-1 codes to limit the results to.
+    pub languages : Option<String>,
+    #[param(inline)]
+    pub sort : Option<Sort>,
+    /// How many of the most common values to return for each facet.  No facets
+    /// are returned unless this is set.
+    pub facets : Option<i64>,
+    /// Whether cross-posts and duplicates are grouped into a single result.
+    /// Defaults to true.
+    pub collapse : Option<bool>,
+    /// The number of posts per page, limited by the server's configuration.
+    pub page_size : Option<i32>,
+    /// The `next` token from the previous page.
+    pub cursor : Option<String>
+}
+
+impl From<PostSearchParams> for SearchQuery {
+    fn from(value : PostSearchParams) -> Self {
+        Self {
+            query : value.query,
+            preferred_instance : value.preferred_instance,
+            languages : value.languages,
+            lang : None,
+            sort : value.sort.map(SearchSort::from),
+            facets : value.facets,
+            collapse : value.collapse,
+            page : None,
+            page_size : value.page_size,
+            cursor : value.cursor,
+            posts : None
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct PostSearchResponse {
+    /// The terms that were searched for, once filters were removed.
+    pub query_terms : Vec<String>,
+    pub total_results : i32,
+    pub total_pages : i32,
+    pub time_taken_ms : u64,
+    pub posts : Vec<Post>,
+    /// Pass this as the `cursor` to get the next page, if there is one.
+    pub next : Option<String>,
+    /// Only returned when `facets` was set.
+    pub facets : Option<Facets>,
+    /// The query with any misspelled words corrected.
+    pub did_you_mean : Option<String>
+}
+
+impl From<SearchResult> for PostSearchResponse {
+    fn from(value : SearchResult) -> Self {
+        let mut query_terms = value.original_query_terms.into_iter()
+            .collect::<Vec<_>>();
+        query_terms.sort();
+
+        Self {
+            query_terms,
+            total_results : value.total_results,
+            total_pages : value.total_pages,
+            time_taken_ms : value.time_taken.as_millis() as u64,
+            posts : value.posts.into_iter().map(Post::from).collect(),
+            next : value.next,
+            facets : value.facets.map(Facets::from),
+            did_you_mean : value.did_you_mean
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Post {
+    /// The id of the post on the preferred instance.
+    pub remote_id : i64,
+    pub name : String,
+    /// The link that was posted, if any.
+    pub url : Option<String>,
+    /// The part of the body that best matches the query.
+    pub snippet : Option<String>,
+    pub highlights : Vec<Highlight>,
+    /// The language the post was tagged with.
+    pub language : Option<String>,
+    /// The language the post appears to be written in, when it wasn't tagged.
+    pub detected_language : Option<String>,
+    pub published : Option<DateTime<Utc>>,
+    pub comments : Option<i64>,
+    pub author : Author,
+    pub community : Community,
+    /// The other posts of the same link that were collapsed into this one.
+    pub also_posted_in : Vec<CrossPost>
+}
+
+impl From<SearchPost> for Post {
+    fn from(value : SearchPost) -> Self {
+        Self {
+            remote_id : value.remote_id,
+            name : value.name,
+            url : value.url,
+            snippet : value.snippet,
+            highlights : value.highlights.into_iter().map(Highlight::from).collect(),
+            language : value.language,
+            detected_language : value.detected_language,
+            published : value.published,
+            comments : value.comments,
+            author : value.author.into(),
+            community : value.community.into(),
+            also_posted_in : value.also_posted_in.into_iter().map(CrossPost::from).collect()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CrossPost {
+    /// The id of the post on the preferred instance.
+    pub remote_id : i64,
+    pub community : Community
+}
+
+impl From<SearchCrossPost> for CrossPost {
+    fn from(value : SearchCrossPost) -> Self {
+        Self {
+            remote_id : value.remote_id,
+            community : value.community.into()
+        }
+    }
+}
+
+/**
+ * Where a match starts and ends in a snippet, counted in characters rather than
+ * bytes.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Highlight {
+    pub start : usize,
+    pub end : usize
+}
+
+impl From<SearchHighlight> for Highlight {
+    fn from(value : SearchHighlight) -> Self {
+        Self {
+            start : value.start,
+            end : value.end
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Author {
+    #[schema(example = "https://lemmy.ml/u/alice")]
+    pub actor_id : String,
+    pub name : String,
+    pub display_name : Option<String>,
+    pub avatar : Option<String>
+}
+
+impl From<SearchAuthor> for Author {
+    fn from(value : SearchAuthor) -> Self {
+        Self {
+            actor_id : value.actor_id,
+            name : value.name,
+            display_name : value.display_name,
+            avatar : value.avatar
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Community {
+    #[schema(example = "https://lemmy.ml/c/rust")]
+    pub actor_id : String,
+    pub name : String,
+    pub title : Option<String>,
+    pub icon : Option<String>
+}
+
+impl From<SearchCommunity> for Community {
+    fn from(value : SearchCommunity) -> Self {
+        Self {
+            actor_id : value.actor_id,
+            name : value.name,
+            title : value.title,
+            icon : value.icon
+        }
+    }
+}
+
+/**
+ * The most common communities, instances, authors and languages among every post
+ * that matched the search.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Facets {
+    pub communities : Vec<Facet>,
+    pub instances : Vec<Facet>,
+    pub authors : Vec<Facet>,
+    pub languages : Vec<Facet>
+}
+
+impl From<SearchFacets> for Facets {
+    fn from(value : SearchFacets) -> Self {
+        Self {
+            communities : value.communities.into_iter().map(Facet::from).collect(),
+            instances : value.instances.into_iter().map(Facet::from).collect(),
+            authors : value.authors.into_iter().map(Facet::from).collect(),
+            languages : value.languages.into_iter().map(Facet::from).collect()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Facet {
+    pub name : String,
+    /// What to add to the query to limit the results to just this value.
+    #[schema(example = "community:!rust@lemmy.ml")]
+    pub filter : String,
+    pub count : i64
+}
+
+impl From<SearchFacet> for Facet {
+    fn from(value : SearchFacet) -> Self {
+        Self {
+            name : value.name,
+            filter : value.filter,
+            count : value.count
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CommunitySearchParams {
+    /// The search query, which may be limited to one instance with `instance:`.
+    pub query : String,
+    /// The domain of the instance that each `url` in the response points to.
+    pub preferred_instance : String,
+    /// Whether to include communities that are marked as NSFW.
+    pub nsfw : Option<bool>,
+    pub page : Option<i32>,
+    pub page_size : Option<i32>
+}
+
+impl From<CommunitySearchParams> for CommunitySearchQuery {
+    fn from(value : CommunitySearchParams) -> Self {
+        Self {
+            query : value.query,
+            preferred_instance : value.preferred_instance,
+            nsfw : value.nsfw,
+            page : value.page,
+            page_size : value.page_size
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CommunitySearchResponse {
+    pub total_results : i32,
+    pub total_pages : i32,
+    pub time_taken_ms : u64,
+    pub communities : Vec<CommunityMatch>
+}
+
+impl From<CommunitySearchResult> for CommunitySearchResponse {
+    fn from(value : CommunitySearchResult) -> Self {
+        Self {
+            total_results : value.total_results,
+            total_pages : value.total_pages,
+            time_taken_ms : value.time_taken.as_millis() as u64,
+            communities : value.communities.into_iter().map(CommunityMatch::from).collect()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CommunityMatch {
+    pub community : Community,
+    /// Where to view the community on the preferred instance.
+    pub url : String,
+    /// The part of the description that best matches the query.
+    pub snippet : Option<String>,
+    pub highlights : Vec<Highlight>,
+    pub nsfw : bool,
+    pub subscribers : Option<i64>,
+    pub active_users_month : Option<i64>,
+    /// How many of the community's posts have been indexed.
+    pub posts : i64
+}
+
+impl From<SearchCommunityResult> for CommunityMatch {
+    fn from(value : SearchCommunityResult) -> Self {
+        Self {
+            community : value.community.into(),
+            url : value.url,
+            snippet : value.snippet,
+            highlights : value.highlights.into_iter().map(Highlight::from).collect(),
+            nsfw : value.nsfw,
+            subscribers : value.subscribers,
+            active_users_month : value.active_users_month,
+            posts : value.posts
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuthorSearchParams {
+    /// The search query, which may be a handle such as `@alice@lemmy.ml`.
+    pub query : String,
+    /// The domain of the instance that each `url` in the response points to.
+    pub preferred_instance : String,
+    pub page : Option<i32>,
+    pub page_size : Option<i32>
+}
+
+impl From<AuthorSearchParams> for AuthorSearchQuery {
+    fn from(value : AuthorSearchParams) -> Self {
+        Self {
+            query : value.query,
+            preferred_instance : value.preferred_instance,
+            page : value.page,
+            page_size : value.page_size
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct AuthorSearchResponse {
+    pub total_results : i32,
+    pub total_pages : i32,
+    pub time_taken_ms : u64,
+    pub authors : Vec<AuthorMatch>
+}
+
+impl From<AuthorSearchResult> for AuthorSearchResponse {
+    fn from(value : AuthorSearchResult) -> Self {
+        Self {
+            total_results : value.total_results,
+            total_pages : value.total_pages,
+            time_taken_ms : value.time_taken.as_millis() as u64,
+            authors : value.authors.into_iter().map(AuthorMatch::from).collect()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct AuthorMatch {
+    pub author : Author,
+    /// Where to view the user's profile on the preferred instance.
+    pub url : String,
+    /// The part of the bio that best matches the query.
+    pub snippet : Option<String>,
+    pub highlights : Vec<Highlight>,
+    /// How many of the user's posts have been indexed.
+    pub posts : i64
+}
+
+impl From<SearchAuthorResult> for AuthorMatch {
+    fn from(value : SearchAuthorResult) -> Self {
+        Self {
+            author : value.author.into(),
+            url : value.url,
+            snippet : value.snippet,
+            highlights : value.highlights.into_iter().map(Highlight::from).collect(),
+            posts : value.posts
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DiscussionParams {
+    /// The link to find the discussions of.
+    #[param(example = "https://blog.rust-lang.org/2023/06/01/Rust-1.70.0.html")]
+    pub url : String,
+    /// The domain of the instance the posts are viewed from.
+    pub preferred_instance : String
+}
+
+impl From<DiscussionParams> for DiscussionQuery {
+    fn from(value : DiscussionParams) -> Self {
+        Self {
+            url : value.url,
+            preferred_instance : value.preferred_instance
+        }
+    }
+}
+
+/**
+ * Every post in a single community that links to the same page.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Discussion {
+    pub community : Community,
+    pub posts : Vec<Post>
+}
+
+impl From<SearchDiscussion> for Discussion {
+    fn from(value : SearchDiscussion) -> Self {
+        Self {
+            community : value.community.into(),
+            posts : value.posts.into_iter().map(Post::from).collect()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SuggestParams {
+    /// What the user has typed so far; only the last term is completed.
+    #[param(example = "rust rel")]
+    pub prefix : String
+}
+
+impl From<SuggestParams> for SuggestQuery {
+    fn from(value : SuggestParams) -> Self {
+        Self {
+            prefix : value.prefix
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Suggestion {
+    /// The whole query, with the last term replaced by the completion.
+    #[schema(example = "rust release")]
+    pub text : String,
+    #[schema(example = "release")]
+    pub term : String,
+    /// How many posts the completed term appears in.
+    pub count : i64
+}
+
+impl From<SearchSuggestion> for Suggestion {
+    fn from(value : SearchSuggestion) -> Self {
+        Self {
+            text : value.text,
+            term : value.term,
+            count : value.count
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use std::{
+    fs::File, 
+    path::PathBuf,
+    io::{
+        BufWriter, 
+        Write
+    }
+};
+use crate::{
+    config::{
+        Config, 
+        handle::ConfigHandle
+    }, 
+    crawler::{
+        crawler::Crawler, 
+        Runner
+    }, 
+    database::{
+        Database, 
+        dbo::{
+            crawler::CrawlerDatabase, 
+            maintenance::MaintenanceDatabase
+        }
+    }, 
+    error::{
+        Result, 
+        LemmySearchError
+    }
+};
+use super::Command;
+
+impl Command {
+
+    const BATCH_SIZE : i64 = 500;
+
+    /**
+     * Runs any of the one-shot commands.  `Command::Serve` is handled by main
+     * directly as it needs to start the HTTP server.
+     */
+    pub async fn execute(
+        self,
+        config : &Config,
+        database : Database
+    ) -> Result<()> {
+        match self {
+            Command::Serve => Err(LemmySearchError::Generic("'serve' is not a one-shot command")),
+            Command::Crawl { instance, once } => Self::crawl(config, database, instance, once).await,
+            Command::Migrate => Self::migrate(),
+            Command::Reindex => Self::reindex(config, database).await,
+            Command::Purge { instance } => Self::purge(database, &instance).await,
+            Command::Export { output } => Self::export(database, output).await,
+            Command::Stats => Self::stats(database).await
+        }
+    }
+
+    async fn crawl(
+        config : &Config,
+        database : Database,
+        instance : Option<String>,
+        once : bool
+    ) -> Result<()> {
+        let mut config = config.clone();
+        if let Some(instance) = instance {
+            config.crawler.seed_instance = instance;
+        }
+
+        println!("Crawler is starting to index '{}'...", config.crawler.seed_instance);
+
+        Crawler::new(
+            config.crawler.seed_instance.clone(), 
+            config.clone(), 
+            database.pool.clone(), 
+            false
+        )?.crawl()
+            .await?;
+
+        println!("Crawling complete.");
+
+        if !once {
+            config.crawler.enabled = true;
+
+            let config = ConfigHandle::new(config, None);
+
+            let mut runner = Runner::new(&config, database);
+            runner.start();
+
+            println!("Waiting for the next scheduled crawl; press Ctrl+C to exit...");
+            tokio::signal::ctrl_c()
+                .await?;
+
+            runner.stop();
+        }
+
+        Ok(())
+    }
+
+    fn migrate(
+
+    ) -> Result<()> {
+        // The schema has already been created/updated when connecting to the database.
+        println!("Database schema is up to date.");
+
+        Ok(())
+    }
+
+    /**
+     * Rebuilds the index from the posts that are already stored.  The old index
+     * is only replaced once the new one is complete, and searches keep using it
+     * until then.
+     */
+    async fn reindex(
+        config : &Config,
+        database : Database
+    ) -> Result<()> {
+        let maintenance = MaintenanceDatabase::new(database.pool.clone());
+        let mut crawler_database = CrawlerDatabase::init(database.pool.clone(), config.crawler.index_code)
+            .await?;
+
+        println!("Rebuilding the index...");
+        crawler_database.begin_reindex()
+            .await?;
+
+        match Self::reindex_posts(&maintenance, &mut crawler_database).await {
+            Ok(total) => {
+                crawler_database.commit_reindex()
+                    .await?;
+
+                println!("Reindex of {} posts complete.", total);
+
+                Ok(())
+            },
+            Err(err) => {
+                println!("Reindex failed, keeping the existing index.");
+
+                crawler_database.rollback_reindex()
+                    .await?;
+
+                Err(err)
+            }
+        }
+    }
+
+    async fn reindex_posts(
+        maintenance : &MaintenanceDatabase,
+        crawler_database : &mut CrawlerDatabase
+    ) -> Result<usize> {
+        let mut total = 0;
+        let mut last_ap_id = String::new();
+        loop {
+            let mut posts = maintenance.get_posts(&last_ap_id, Self::BATCH_SIZE)
+                .await?;
+
+            match posts.last() {
+                Some(post) => last_ap_id = post.post.ap_id.clone(),
+                None => break
+            }
+
+            // Posts crawled by older versions may be missing anything that the
+            // crawler now works out for itself, such as the detected language.
+            for post in posts.iter_mut() {
+                if post.post.detected_language.is_none() {
+                    post.post.detect_language();
+                }
+                post.post.normalize_url();
+            }
+            maintenance.update_derived_fields(&posts)
+                .await?;
+
+            crawler_database.bulk_reindex_post(&posts)
+                .await?;
+
+            total += posts.len();
+            println!("\treindexed {} posts...", total);
+        }
+
+        Ok(total)
+    }
+
+    async fn purge(
+        database : Database,
+        instance : &str
+    ) -> Result<()> {
+        let instance_actor_id = format!("https://{}/", instance.trim_end_matches('/'));
+
+        println!("Purging '{}'...", instance_actor_id);
+
+        let count = MaintenanceDatabase::new(database.pool)
+            .purge_instance(&instance_actor_id)
+            .await?;
+
+        println!("\t...removed {} posts.", count);
+
+        Ok(())
+    }
+
+    async fn export(
+        database : Database,
+        output : PathBuf
+    ) -> Result<()> {
+        let maintenance = MaintenanceDatabase::new(database.pool);
+
+        println!("Exporting posts to '{}'...", output.display());
+
+        let mut writer = BufWriter::new(File::create(output)?);
+        let mut total = 0;
+
+        let mut last_ap_id = String::new();
+        loop {
+            let posts = maintenance.get_posts(&last_ap_id, Self::BATCH_SIZE)
+                .await?;
+
+            match posts.last() {
+                Some(post) => last_ap_id = post.post.ap_id.clone(),
+                None => break
+            }
+
+            for post in posts {
+                let line = serde_json::to_string(&post)
+                    .map_err(|err| {
+                        LemmySearchError::Unknown(err.to_string())
+                    })?;
+                writeln!(writer, "{}", line)?;
+                total += 1;
+            }
+        }
+
+        writer.flush()?;
+
+        println!("\t...exported {} posts.", total);
+
+        Ok(())
+    }
+
+    async fn stats(
+        database : Database
+    ) -> Result<()> {
+        let counts = MaintenanceDatabase::new(database.pool)
+            .get_table_counts()
+            .await?;
+
+        for (table, count) in counts {
+            println!("{:<12}{:>12}", table, count);
+        }
+
+        Ok(())
+    }
+}
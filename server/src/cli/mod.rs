@@ -0,0 +1,65 @@
+pub mod commands;
+
+use std::path::PathBuf;
+use clap::{
+    Parser, 
+    Subcommand
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Args {
+    /// The location of the config.yml file.  If omitted the default location of
+    /// '/lemmy/config/config.yml' is used, if it exists.
+    #[arg(long, global = true, env = "LEMMY_SEARCH_CONFIG")]
+    pub config : Option<PathBuf>,
+
+    /// Only serve the API; the static UI files are expected to be hosted elsewhere.
+    #[arg(long, global = true, env = "LEMMY_SEARCH_NO_UI")]
+    pub no_ui : bool,
+
+    #[command(subcommand)]
+    pub command : Option<Command>
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the HTTP server along with the crawler's scheduler.  This is the
+    /// default when no command is given.
+    Serve,
+
+    /// Crawl an instance right away, then keep crawling it at the regular
+    /// interval unless `--once` is given.
+    Crawl {
+        /// The instance to crawl.  Defaults to the `seed_instance` from the config.
+        #[arg(long)]
+        instance : Option<String>,
+
+        /// Exit as soon as the crawl has finished.
+        #[arg(long)]
+        once : bool
+    },
+
+    /// Create or update the database schema and then exit.
+    Migrate,
+
+    /// Rebuild the search index from the posts that are already stored.
+    Reindex,
+
+    /// Remove all posts, communities and authors that belong to an instance.
+    Purge {
+        /// The instance to remove, i.e. 'lemmy.ml'.
+        #[arg(long)]
+        instance : String
+    },
+
+    /// Write every indexed post out as JSON, one post per line.
+    Export {
+        /// The file to write to.
+        #[arg(long)]
+        output : PathBuf
+    },
+
+    /// Print the number of rows in each table of the index.
+    Stats
+}
@@ -0,0 +1,137 @@
+use std::{
+    path::PathBuf, 
+    sync::{
+        Arc, 
+        RwLock
+    }, 
+    time::{
+        Duration, 
+        SystemTime
+    }
+};
+use tokio::{
+    signal::unix::{
+        signal, 
+        SignalKind
+    }, 
+    task::JoinHandle
+};
+use crate::error::{
+    Result, 
+    LogError
+};
+use super::Config;
+
+/**
+ * A shared handle to the current config that can be reloaded while the server
+ * is running.  Anything that should pick up changes without a restart, such as
+ * the crawler, should call `get()` each time it needs the config rather than
+ * holding on to a copy.
+ */
+#[derive(Clone)]
+pub struct ConfigHandle {
+    path : Option<PathBuf>,
+    config : Arc<RwLock<Config>>
+}
+
+impl ConfigHandle {
+
+    const POLL_INTERVAL : Duration = Duration::from_secs(5);
+
+    pub fn new(
+        config : Config,
+        path : Option<PathBuf>
+    ) -> Self {
+        Self {
+            path,
+            config : Arc::new(RwLock::new(config))
+        }
+    }
+
+    pub fn get(
+        &self
+    ) -> Config {
+        self.config.read()
+            .unwrap()
+            .clone()
+    }
+
+    /**
+     * Loads the config again from all of its sources.  If the new config is
+     * invalid the current one is left untouched.
+     */
+    pub fn reload(
+        &self
+    ) -> Result<()> {
+        let config = Config::load(self.path.as_deref())?;
+
+        let mut current = self.config.write()
+            .unwrap();
+
+        if config.server != current.server || config.postgres != current.postgres {
+            println!("\t...changes to the 'server' and 'postgres' settings require a restart.");
+        }
+
+        *current = config;
+
+        Ok(())
+    }
+
+    /**
+     * Reloads the config whenever the process receives a SIGHUP or the config
+     * file has been modified.
+     */
+    pub fn watch(
+        &self
+    ) -> JoinHandle<()> {
+        let handle = self.clone();
+
+        tokio::spawn(async move {
+            let mut hangup = match signal(SignalKind::hangup()) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    println!("Unable to listen for SIGHUP; only watching the config file...");
+                    println!("{}", err);
+                    None
+                }
+            };
+
+            let mut last_modified = handle.get_last_modified();
+            let mut interval = tokio::time::interval(Self::POLL_INTERVAL);
+
+            loop {
+                tokio::select! {
+                    Some(_) = async {
+                        match hangup.as_mut() {
+                            Some(hangup) => hangup.recv().await,
+                            None => None
+                        }
+                    } => {
+                        println!("Received SIGHUP; reloading config...");
+                    },
+                    _ = interval.tick() => {
+                        let modified = handle.get_last_modified();
+                        if modified == last_modified {
+                            continue;
+                        }
+                        last_modified = modified;
+                        println!("Config file changed; reloading config...");
+                    }
+                }
+
+                let _ = handle.reload()
+                    .log_error("Failed to reload config; keeping the previous config.", true);
+            }
+        })
+    }
+
+    fn get_last_modified(
+        &self
+    ) -> Option<SystemTime> {
+        std::fs::metadata(Config::get_path(self.path.as_deref()))
+            .and_then(|metadata| {
+                metadata.modified()
+            })
+            .ok()
+    }
+}
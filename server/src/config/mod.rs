@@ -1,22 +1,123 @@
-use config_file::FromConfigFile;
+pub mod handle;
+
+use std::{
+    collections::HashMap,
+    path::{
+        Path, 
+        PathBuf
+    }
+};
+use ::config::{
+    Environment, 
+    File, 
+    FileFormat
+};
 use serde::Deserialize;
+use crate::error::Result;
 
 #[derive(Debug, Deserialize, Default, Clone)]
 pub struct Config {
     pub development_mode : bool,
+    pub server : Server,
+    pub site : Site,
+    pub search : Search,
+    pub saved_searches : SavedSearches,
     pub crawler : Crawler,
     pub postgres : Postgres,
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+pub struct Server {
+    pub bind_address : String,
+    pub port : u16,
+    pub workers : Option<usize>,
+    pub unix_socket : Option<String>,
+    pub ui_directory : String
+}
+
+/**
+ * How the site describes itself to browsers, such as in its OpenSearch description.
+ */
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+pub struct Site {
+    pub name : String,
+    pub base_url : Option<String>,
+    pub icon : String
+}
+
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+pub struct Search {
+    pub page_size : i32,
+    pub max_page_size : i32
+}
+
+impl Search {
+
+    /**
+     * The number of results to return per page, using the default if none was
+     * asked for, and never more than the maximum.
+     */
+    pub fn get_page_size(
+        &self,
+        requested : Option<i32>
+    ) -> i32 {
+        requested.unwrap_or(self.page_size)
+            .clamp(1, self.max_page_size.max(1))
+    }
+}
+
+/**
+ * Searches that are saved along with a webhook, which is sent any new posts that
+ * match them after each crawl.
+ */
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
+pub struct SavedSearches {
+    pub enabled : bool,
+    /// When given, webhooks can only be sent to these hosts, which are trusted
+    /// even if they're on a private network.  Otherwise any host can be used as
+    /// long as it only resolves to public addresses.
+    pub allowed_webhook_hosts : Option<Vec<String>>
+}
+
+#[derive(Debug, Deserialize, Default, Clone, PartialEq)]
 pub struct Crawler {
     pub enabled : bool,
     pub seed_instance : String,
     pub single_instance_only : Option<bool>,
+    pub interval_hours : u32,
+    pub allowed_instances : Option<Vec<String>>,
+    pub blocked_instances : Option<Vec<String>>,
+    pub index_code : bool,
     pub log : bool
 }
 
-#[derive(Debug, Deserialize, Default, Clone)]
+impl Crawler {
+
+    /**
+     * Checks the instance's domain against the allow and block lists.  An
+     * instance is allowed if it's not blocked, and either no allow list was
+     * given or the instance appears on it.
+     */
+    pub fn is_instance_allowed(
+        &self,
+        domain : &str
+    ) -> bool {
+        let domain = domain.to_lowercase();
+        if self.blocked_instances.iter().flatten().any(|blocked| {
+            blocked.to_lowercase() == domain
+        }) {
+            return false;
+        }
+        match &self.allowed_instances {
+            Some(allowed) if !allowed.is_empty() => allowed.iter().any(|allowed| {
+                allowed.to_lowercase() == domain
+            }),
+            _ => true
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone, PartialEq)]
 pub struct Postgres {
     pub user : String,
     pub password : String,
@@ -24,29 +125,261 @@ pub struct Postgres {
     pub port : u16,
     pub database : String,
     pub log : bool,
-    pub max_size : usize
+    pub max_size : usize,
+    pub startup_timeout : Option<u64>
+}
+
+impl std::fmt::Debug for Postgres {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never write the password out to the logs.
+        f.debug_struct("Postgres")
+            .field("user", &self.user)
+            .field("password", &"********")
+            .field("hostname", &self.hostname)
+            .field("port", &self.port)
+            .field("database", &self.database)
+            .field("log", &self.log)
+            .field("max_size", &self.max_size)
+            .field("startup_timeout", &self.startup_timeout)
+            .finish()
+    }
 }
 
 impl Config {
 
     const DEFAULT_CONFIG_LOCATION : &str = "/lemmy/config/config.yml"; 
+    const ENVIRONMENT_PREFIX : &str = "LEMMY_SEARCH";
+    const ENVIRONMENT_SEPARATOR : &str = "__";
+
+    /**
+     * Builds the config by layering, in order of precedence (lowest first):
+     *  * The built-in defaults.
+     *  * The config file at `path`, or the default location if none was given.
+     *  * Environment variables such as `LEMMY_SEARCH__POSTGRES__PASSWORD`.
+     * 
+     * An explicitly provided config file must exist.  Any required field that
+     * is missing from all of the layers is treated as an error.
+     */
+    pub fn load(
+        path : Option<&Path>
+    ) -> Result<Self> {
+        let environment = std::env::vars_os()
+            .filter_map(|(name, value)| {
+                Some((name.into_string().ok()?, value.into_string().ok()?))
+            })
+            .collect();
+
+        Self::load_from(path, environment)
+    }
 
-    pub fn load() -> Self {
-        let result = Config::from_config_file(Self::DEFAULT_CONFIG_LOCATION);
-        match result {
-            Ok(value) => {
-                println!("Config loaded from '{}'...", Self::DEFAULT_CONFIG_LOCATION);
-                println!("{:?}", value);
-                value
-            },
-            Err(err) => {
-                println!("Failed to load config file...");
-                println!("{}", err);
-                Config {
-                    ..Default::default()
-                }
-            }
+    /**
+     * The same as `load`, but with the given environment variables in place of
+     * the process's own.
+     */
+    fn load_from(
+        path : Option<&Path>,
+        environment : HashMap<String, String>
+    ) -> Result<Self> {
+        let file = match path {
+            Some(path) => File::from(path)
+                .format(FileFormat::Yaml)
+                .required(true),
+            None => File::with_name(Self::DEFAULT_CONFIG_LOCATION)
+                .format(FileFormat::Yaml)
+                .required(false)
+        };
+
+        let config = ::config::Config::builder()
+            .set_default("development_mode", false)?
+            .set_default("server.bind_address", "0.0.0.0")?
+            .set_default("server.port", 8000)?
+            .set_default("server.ui_directory", "./ui")?
+            .set_default("site.name", "Lemmy Search")?
+            .set_default("site.icon", "/imgs/lemmy.svg")?
+            .set_default("search.page_size", 50)?
+            .set_default("search.max_page_size", 100)?
+            .set_default("saved_searches.enabled", false)?
+            .set_default("crawler.enabled", false)?
+            .set_default("crawler.interval_hours", 6)?
+            .set_default("crawler.index_code", true)?
+            .set_default("crawler.log", false)?
+            .set_default("postgres.hostname", "localhost")?
+            .set_default("postgres.port", 5432)?
+            .set_default("postgres.log", false)?
+            .set_default("postgres.max_size", 15)?
+            .add_source(file)
+            .add_source(
+                Environment::with_prefix(Self::ENVIRONMENT_PREFIX)
+                    .separator(Self::ENVIRONMENT_SEPARATOR)
+                    .try_parsing(true)
+                    .list_separator(",")
+                    .with_list_parse_key("crawler.allowed_instances")
+                    .with_list_parse_key("crawler.blocked_instances")
+                    .with_list_parse_key("saved_searches.allowed_webhook_hosts")
+                    .source(Some(environment.clone()))
+            )
+            .build()?
+            .try_deserialize::<Config>()?;
+
+        // The default config file is optional, so only mention the sources that
+        // were actually found.  Only the names of the environment variables are
+        // logged, as they may hold secrets.
+        let file_path = Self::get_path(path);
+        println!("Config loaded from the defaults...");
+        if file_path.exists() {
+            println!("\t...and '{}'", file_path.display());
+        } else {
+            println!("\t...no config file was found at '{}'", file_path.display());
         }
+        let environment_variables = Self::get_environment_variables(&environment);
+        if !environment_variables.is_empty() {
+            println!("\t...and the environment variables {}", environment_variables.join(", "));
+        }
+        println!("{:?}", config);
+
+        Ok(config)
     }
 
+    /**
+     * The names of the environment variables that override the config.
+     */
+    fn get_environment_variables(
+        environment : &HashMap<String, String>
+    ) -> Vec<String> {
+        let prefix = format!("{}{}", Self::ENVIRONMENT_PREFIX, Self::ENVIRONMENT_SEPARATOR)
+            .to_lowercase();
+
+        let mut names = environment.keys()
+            .filter(|name| {
+                name.to_lowercase().starts_with(&prefix)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /**
+     * The location that the config file is, or would be, loaded from.
+     */
+    pub fn get_path(
+        path : Option<&Path>
+    ) -> PathBuf {
+        path.map(|path| {
+            path.to_path_buf()
+        }).unwrap_or(PathBuf::from(Self::DEFAULT_CONFIG_LOCATION))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        path::Path
+    };
+    use super::{
+        Config,
+        Crawler,
+        Search
+    };
+
+    #[test]
+    fn layers_the_file_and_environment_over_the_defaults() {
+        let path = std::env::temp_dir()
+            .join(format!("lemmy-search-config-{}.yml", std::process::id()));
+        std::fs::write(&path, "
+server:
+  port: 9000
+crawler:
+  seed_instance: lemmy.ml
+  allowed_instances:
+    - lemmy.ml
+postgres:
+  user: lemmy
+  password: file
+  database: lemmy
+").unwrap();
+
+        let environment = HashMap::from([
+            ("LEMMY_SEARCH__POSTGRES__PASSWORD".to_string(), "environment".to_string()),
+            ("LEMMY_SEARCH__CRAWLER__BLOCKED_INSTANCES".to_string(), "a.example,b.example".to_string()),
+            ("OTHER__POSTGRES__PASSWORD".to_string(), "other".to_string())
+        ]);
+        let config = Config::load_from(Some(&path), environment);
+        std::fs::remove_file(&path).unwrap();
+
+        let config = config.unwrap();
+
+        // Only in the defaults.
+        assert_eq!(config.server.bind_address, "0.0.0.0");
+        assert_eq!(config.search.page_size, 50);
+        assert_eq!(config.postgres.port, 5432);
+
+        // The file overrides the defaults.
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.crawler.seed_instance, "lemmy.ml");
+        assert_eq!(config.crawler.allowed_instances, Some(vec!["lemmy.ml".to_string()]));
+
+        // The environment overrides the file, and lists are split on commas.
+        assert_eq!(config.postgres.password, "environment");
+        assert_eq!(config.crawler.blocked_instances, Some(vec!["a.example".to_string(), "b.example".to_string()]));
+    }
+
+    #[test]
+    fn requires_an_explicit_config_file_to_exist() {
+        assert!(Config::load(Some(Path::new("/nonexistent/config.yml"))).is_err());
+    }
+
+    #[test]
+    fn allows_any_instance_without_lists() {
+        let crawler = Crawler::default();
+        assert!(crawler.is_instance_allowed("lemmy.ml"));
+
+        let crawler = Crawler {
+            allowed_instances : Some(Vec::new()),
+            ..Default::default()
+        };
+        assert!(crawler.is_instance_allowed("lemmy.ml"));
+    }
+
+    #[test]
+    fn only_allows_listed_instances() {
+        let crawler = Crawler {
+            allowed_instances : Some(vec!["Lemmy.ml".to_string()]),
+            ..Default::default()
+        };
+        assert!(crawler.is_instance_allowed("lemmy.ml"));
+        assert!(crawler.is_instance_allowed("LEMMY.ML"));
+        assert!(!crawler.is_instance_allowed("lemmy.world"));
+    }
+
+    #[test]
+    fn blocks_instances_even_if_allowed() {
+        let crawler = Crawler {
+            allowed_instances : Some(vec!["lemmy.ml".to_string(), "lemmy.world".to_string()]),
+            blocked_instances : Some(vec!["LEMMY.WORLD".to_string()]),
+            ..Default::default()
+        };
+        assert!(crawler.is_instance_allowed("lemmy.ml"));
+        assert!(!crawler.is_instance_allowed("lemmy.world"));
+    }
+
+    #[test]
+    fn clamps_the_page_size() {
+        let search = Search {
+            page_size : 50,
+            max_page_size : 100
+        };
+        assert_eq!(search.get_page_size(None), 50);
+        assert_eq!(search.get_page_size(Some(20)), 20);
+        assert_eq!(search.get_page_size(Some(500)), 100);
+        assert_eq!(search.get_page_size(Some(0)), 1);
+        assert_eq!(search.get_page_size(Some(-5)), 1);
+
+        let search = Search {
+            page_size : 50,
+            max_page_size : 0
+        };
+        assert_eq!(search.get_page_size(None), 1);
+    }
 }
@@ -1,40 +1,377 @@
-use std::collections::HashSet;
-
+use std::collections::{
+    HashMap,
+    HashSet
+};
+use lazy_static::lazy_static;
+use pulldown_cmark::{
+    Event,
+    Parser,
+    Tag
+};
+use regex::Regex;
+use rust_stemmers::{
+    Algorithm,
+    Stemmer
+};
+use whatlang::Lang;
+use url::Url;
 use crate::api::lemmy::models::{
-    post::Post, 
+    post::{
+        Post,
+        PostData
+    },
     comment::Comment
 };
 
+lazy_static! {
+    static ref STOP_WORDS : HashMap<SupportedLanguage, HashSet<&'static str>> = SupportedLanguage::ALL
+        .into_iter()
+        .map(|language| {
+            (language, stop_words::get(language.get_code()).iter().copied().collect())
+        }).collect();
+
+    static ref BARE_LINK : Regex = Regex::new(r"https?://[^\s<>()\[\]]+").unwrap();
+    static ref SPOILER_MARKER : Regex = Regex::new(r"(?m)^\s*:::\s*(spoiler)?").unwrap();
+}
+
+/**
+ * The part of a post that a word was found in.  As the same word can appear in
+ * several parts of a post, these are stored together as a set of flags.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexField {
+    Title,
+    Body,
+    Code,
+    Link,
+    Community,
+    Comment
+}
+
+impl IndexField {
+
+    pub const ALL : [IndexField; 6] = [
+        Self::Title,
+        Self::Body,
+        Self::Code,
+        Self::Link,
+        Self::Community,
+        Self::Comment
+    ];
+
+    /**
+     * The flag for every field, for query terms that may match anywhere.
+     */
+    pub fn get_all_flags(
+
+    ) -> i16 {
+        Self::ALL.iter().fold(0, |flags, field| {
+            flags | field.get_flag()
+        })
+    }
+
+    /**
+     * Looks up the field that a query term can be limited to with a prefix, such
+     * as `title:rust`.
+     */
+    pub fn from_prefix(
+        prefix : &str
+    ) -> Option<Self> {
+        match prefix {
+            "title" => Some(Self::Title),
+            "body" => Some(Self::Body),
+            "code" => Some(Self::Code),
+            _ => None
+        }
+    }
+
+    /**
+     * Body keeps the flag that all text had before fields were split out, and
+     * is also what cross-references without any flags are treated as.
+     */
+    pub fn get_flag(
+        &self
+    ) -> i16 {
+        match self {
+            Self::Body => 1 << 0,
+            Self::Code => 1 << 1,
+            Self::Title => 1 << 2,
+            Self::Link => 1 << 3,
+            Self::Community => 1 << 4,
+            Self::Comment => 1 << 5
+        }
+    }
+
+    /**
+     * How much a match in this field counts towards a post's relevance.
+     */
+    pub fn get_weight(
+        &self
+    ) -> i32 {
+        match self {
+            Self::Title => 10,
+            Self::Community => 6,
+            Self::Link => 4,
+            Self::Body => 2,
+            Self::Code => 1,
+            Self::Comment => 1
+        }
+    }
+}
+
+/**
+ * The text of a Markdown document split up by how it would be shown to a reader.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownText {
+    pub text : String,
+    pub code : String,
+    pub links : Vec<String>
+}
+
+/**
+ * The languages that the analyzer has both a stemmer and a list of stop words for.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SupportedLanguage {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Turkish
+}
+
+impl SupportedLanguage {
+
+    pub const ALL : [SupportedLanguage; 17] = [
+        Self::Arabic,
+        Self::Danish,
+        Self::Dutch,
+        Self::English,
+        Self::Finnish,
+        Self::French,
+        Self::German,
+        Self::Greek,
+        Self::Hungarian,
+        Self::Italian,
+        Self::Norwegian,
+        Self::Portuguese,
+        Self::Romanian,
+        Self::Russian,
+        Self::Spanish,
+        Self::Swedish,
+        Self::Turkish
+    ];
+
+    /**
+     * Looks up the language from its two letter ISO 639-1 code.
+     */
+    pub fn from_code(
+        code : &str
+    ) -> Option<Self> {
+        Self::ALL.into_iter().find(|language| {
+            language.get_code() == code
+        })
+    }
+
+    pub fn get_code(
+        &self
+    ) -> &'static str {
+        match self {
+            Self::Arabic => "ar",
+            Self::Danish => "da",
+            Self::Dutch => "nl",
+            Self::English => "en",
+            Self::Finnish => "fi",
+            Self::French => "fr",
+            Self::German => "de",
+            Self::Greek => "el",
+            Self::Hungarian => "hu",
+            Self::Italian => "it",
+            Self::Norwegian => "no",
+            Self::Portuguese => "pt",
+            Self::Romanian => "ro",
+            Self::Russian => "ru",
+            Self::Spanish => "es",
+            Self::Swedish => "sv",
+            Self::Turkish => "tr"
+        }
+    }
+
+    fn get_algorithm(
+        &self
+    ) -> Algorithm {
+        match self {
+            Self::Arabic => Algorithm::Arabic,
+            Self::Danish => Algorithm::Danish,
+            Self::Dutch => Algorithm::Dutch,
+            Self::English => Algorithm::English,
+            Self::Finnish => Algorithm::Finnish,
+            Self::French => Algorithm::French,
+            Self::German => Algorithm::German,
+            Self::Greek => Algorithm::Greek,
+            Self::Hungarian => Algorithm::Hungarian,
+            Self::Italian => Algorithm::Italian,
+            Self::Norwegian => Algorithm::Norwegian,
+            Self::Portuguese => Algorithm::Portuguese,
+            Self::Romanian => Algorithm::Romanian,
+            Self::Russian => Algorithm::Russian,
+            Self::Spanish => Algorithm::Spanish,
+            Self::Swedish => Algorithm::Swedish,
+            Self::Turkish => Algorithm::Turkish
+        }
+    }
+
+    pub fn is_stop_word(
+        &self,
+        word : &str
+    ) -> bool {
+        STOP_WORDS[self].contains(word)
+    }
+
+    pub fn stem(
+        &self,
+        word : &str
+    ) -> String {
+        Stemmer::create(self.get_algorithm())
+            .stem(word)
+            .into_owned()
+    }
+}
+
+/**
+ * A single word from the user's query along with every form of it that could
+ * have been stored in the index.
+ */
+#[derive(Debug, Clone)]
+pub struct QueryTerm {
+    pub term : String,
+    pub variants : HashSet<String>,
+    /// The flags of each `IndexField` that the term is allowed to match in.
+    pub fields : i16
+}
+
 pub trait Analyzer {
+
+    /**
+     * Returns every distinct word along with the flags for each of the fields
+     * that it was found in.
+     */
     fn get_distinct_words(
         &self
+    ) -> HashMap<String, i16>;
+
+    fn get_links(
+        &self
     ) -> HashSet<String>;
 }
 
-impl Analyzer for Post {
+impl Analyzer for PostData {
 
     fn get_distinct_words(
         &self
+    ) -> HashMap<String, i16> {
+        let post = &self.post;
+        let language = post.language.as_deref()
+            .or(post.detected_language.as_deref())
+            .and_then(SupportedLanguage::from_code);
+
+        let mut words = HashMap::<String, i16>::new();
+        add_words(&mut words, analyze(&post.name, language), IndexField::Title);
+        if let Some(body) = &post.body {
+            let body = extract_markdown(body);
+            add_words(&mut words, analyze(&body.text, language), IndexField::Body);
+            // Code isn't written in any natural language, so leave it as is.
+            add_words(&mut words, analyze(&body.code, None), IndexField::Code);
+        }
+
+        let domains = post.url.iter()
+            .chain(self.get_links().iter())
+            .filter_map(|url| {
+                get_domain(url)
+            })
+            .collect::<HashSet<_>>();
+        for domain in domains {
+            // The top level domain on its own would match far too many posts.
+            let name = match domain.rsplit_once('.') {
+                Some((name, _)) => name,
+                None => &domain
+            };
+            add_words(&mut words, analyze(name, None), IndexField::Link);
+        }
+
+        add_words(&mut words, analyze(&self.community.name, language), IndexField::Community);
+        if let Some(title) = &self.community.title {
+            add_words(&mut words, analyze(title, language), IndexField::Community);
+        }
+        words
+    }
+
+    fn get_links(
+        &self
     ) -> HashSet<String> {
-        let mut words = HashSet::<String>::new();
-        let name_trimed = self.name.replace(|c : char| {
-            !c.is_ascii_alphanumeric() && !c.is_whitespace()
-        }, " ").to_lowercase();
-        for word in name_trimed.split_whitespace() {
-            words.insert(word.to_lowercase().trim().to_string());
-        }
-        match &self.body {
-            Some(body) => {
-                let body_trimed = body.replace(|c : char| {
-                    !c.is_ascii_alphanumeric() && !c.is_whitespace()
-                }, " ").to_lowercase();
-                for word in body_trimed.split_whitespace() {
-                    words.insert(word.to_lowercase().trim().to_string());
-                }
+        self.post.body.iter().flat_map(|body| {
+            extract_markdown(body).links
+        }).collect()
+    }
+}
+
+impl Post {
+
+    /**
+     * Detects the language of the post's title and body, but only if the post's
+     * author didn't already say which language it was written in.  Detections
+     * that the detector doesn't consider reliable are discarded.
+     */
+    pub fn detect_language(
+        &mut self
+    ) {
+        if self.language.is_some() {
+            return;
+        }
+
+        let text = match &self.body {
+            Some(body) => format!("{}\n{}", self.name, body),
+            None => self.name.clone()
+        };
+
+        match whatlang::detect(&text) {
+            Some(info) if info.is_reliable() => {
+                self.detected_language = Some(get_iso_639_1(info.lang()).to_string());
+                self.language_confidence = Some(info.confidence() as f32);
             },
-            None => {}
+            _ => {
+                self.detected_language = None;
+                self.language_confidence = None;
+            }
         }
-        words
+    }
+}
+
+impl Post {
+
+    /**
+     * Fills in the domain and normalized form of the post's link, if it has one.
+     */
+    pub fn normalize_url(
+        &mut self
+    ) {
+        self.domain = self.url.as_deref()
+            .and_then(get_domain);
+        self.normalized_url = self.url.as_deref()
+            .and_then(normalize_url);
     }
 }
 
@@ -42,11 +379,495 @@ impl Analyzer for Comment {
 
     fn get_distinct_words(
         &self,
+    ) -> HashMap<String, i16> {
+        let content = extract_markdown(&self.content);
+
+        let mut words = HashMap::<String, i16>::new();
+        add_words(&mut words, analyze(&content.text, None), IndexField::Comment);
+        add_words(&mut words, analyze(&content.code, None), IndexField::Code);
+        words
+    }
+
+    fn get_links(
+        &self
     ) -> HashSet<String> {
-        HashSet::from_iter(self.content.replace(|c : char| {
-            !c.is_ascii_alphanumeric() && !c.is_whitespace()
-        }, " ").to_lowercase().split_whitespace().map(|word|
-            word.to_lowercase().trim().to_string()
-        ))
+        extract_markdown(&self.content).links
+            .into_iter()
+            .collect()
+    }
+}
+
+fn add_words(
+    words : &mut HashMap<String, i16>,
+    new_words : HashSet<String>,
+    field : IndexField
+) {
+    for word in new_words {
+        *words.entry(word).or_insert(0) |= field.get_flag();
+    }
+}
+
+/**
+ * Gets the lowercase host name of the url, without any leading 'www.'.
+ */
+pub fn get_domain(
+    url : &str
+) -> Option<String> {
+    let host = Url::parse(url).ok()?
+        .host_str()?
+        .to_lowercase();
+
+    Some(host.strip_prefix("www.")
+        .map(|host| {
+            host.to_string()
+        })
+        .unwrap_or(host))
+}
+
+/**
+ * Reduces the url down to its domain, path and query so that the same page can be
+ * matched regardless of its scheme, a leading 'www.', a trailing '/' or fragment.
+ * A url without a scheme is assumed to be https.
+ */
+pub fn normalize_url(
+    url : &str
+) -> Option<String> {
+    let url = if url.contains("://") {
+        Url::parse(url)
+    } else {
+        Url::parse(&format!("https://{}", url))
+    }.ok()?;
+
+    let domain = get_domain(url.as_str())?;
+    let path = url.path()
+        .trim_end_matches('/');
+
+    Some(match url.query() {
+        Some(query) => format!("{}{}?{}", domain, path, query),
+        None => format!("{}{}", domain, path)
+    })
+}
+
+/**
+ * Parses the Markdown so that only what a reader would actually see is indexed
+ * as text.  Code, both inline and in blocks, is kept separately and the targets
+ * of any links or images, including bare URLs, are pulled out rather than being
+ * split up into words.  Lemmy's spoiler markers are dropped but their contents
+ * are kept.
+ */
+pub fn extract_markdown(
+    markdown : &str
+) -> MarkdownText {
+    let markdown = SPOILER_MARKER.replace_all(markdown, "");
+
+    let mut result = MarkdownText::default();
+    let mut in_code_block = false;
+
+    for event in Parser::new(&markdown) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+            },
+            Event::End(Tag::CodeBlock(_)) => {
+                in_code_block = false;
+                result.code.push('\n');
+            },
+            Event::Start(Tag::Link(_, url, _)) | Event::Start(Tag::Image(_, url, _)) => {
+                result.links.push(url.to_string());
+            },
+            Event::Text(text) if in_code_block => {
+                result.code.push_str(&text);
+            },
+            Event::Text(text) => {
+                for link in BARE_LINK.find_iter(&text) {
+                    result.links.push(link.as_str().to_string());
+                }
+                result.text.push_str(&BARE_LINK.replace_all(&text, " "));
+            },
+            Event::Code(code) => {
+                result.code.push_str(&code);
+                result.code.push('\n');
+            },
+            Event::SoftBreak | Event::HardBreak | Event::Rule => {
+                result.text.push('\n');
+            },
+            Event::End(Tag::Emphasis) | Event::End(Tag::Strong) | Event::End(Tag::Strikethrough) | Event::End(Tag::Link(..)) => {},
+            Event::End(_) => {
+                result.text.push('\n');
+            },
+            _ => {}
+        }
+    }
+
+    result.links.dedup();
+    result
+}
+
+/**
+ * Lemmy identifies languages by their ISO 639-1 code, where as the detector uses
+ * ISO 639-3, so translate between the two.
+ */
+fn get_iso_639_1(
+    lang : Lang
+) -> &'static str {
+    match lang {
+        Lang::Epo => "eo",
+        Lang::Eng => "en",
+        Lang::Rus => "ru",
+        Lang::Cmn => "zh",
+        Lang::Spa => "es",
+        Lang::Por => "pt",
+        Lang::Ita => "it",
+        Lang::Ben => "bn",
+        Lang::Fra => "fr",
+        Lang::Deu => "de",
+        Lang::Ukr => "uk",
+        Lang::Kat => "ka",
+        Lang::Ara => "ar",
+        Lang::Hin => "hi",
+        Lang::Jpn => "ja",
+        Lang::Heb => "he",
+        Lang::Yid => "yi",
+        Lang::Pol => "pl",
+        Lang::Amh => "am",
+        Lang::Jav => "jv",
+        Lang::Kor => "ko",
+        Lang::Nob => "no",
+        Lang::Dan => "da",
+        Lang::Swe => "sv",
+        Lang::Fin => "fi",
+        Lang::Tur => "tr",
+        Lang::Nld => "nl",
+        Lang::Hun => "hu",
+        Lang::Ces => "cs",
+        Lang::Ell => "el",
+        Lang::Bul => "bg",
+        Lang::Bel => "be",
+        Lang::Mar => "mr",
+        Lang::Kan => "kn",
+        Lang::Ron => "ro",
+        Lang::Slv => "sl",
+        Lang::Hrv => "hr",
+        Lang::Srp => "sr",
+        Lang::Mkd => "mk",
+        Lang::Lit => "lt",
+        Lang::Lav => "lv",
+        Lang::Est => "et",
+        Lang::Tam => "ta",
+        Lang::Vie => "vi",
+        Lang::Urd => "ur",
+        Lang::Tha => "th",
+        Lang::Guj => "gu",
+        Lang::Uzb => "uz",
+        Lang::Pan => "pa",
+        Lang::Aze => "az",
+        Lang::Ind => "id",
+        Lang::Tel => "te",
+        Lang::Pes => "fa",
+        Lang::Mal => "ml",
+        Lang::Ori => "or",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Sin => "si",
+        Lang::Khm => "km",
+        Lang::Tuk => "tk",
+        Lang::Aka => "ak",
+        Lang::Zul => "zu",
+        Lang::Sna => "sn",
+        Lang::Afr => "af",
+        Lang::Lat => "la",
+        Lang::Slk => "sk",
+        Lang::Cat => "ca",
+        Lang::Tgl => "tl",
+        Lang::Hye => "hy"
+    }
+}
+
+/**
+ * Splits the text up into lowercase words, treating anything that isn't
+ * alphanumeric as a word boundary.
+ */
+pub fn tokenize(
+    text : &str
+) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c : char| {
+            !c.is_alphanumeric()
+        })
+        .filter(|word| {
+            !word.is_empty()
+        })
+        .map(|word| {
+            word.to_string()
+        })
+        .collect()
+}
+
+/**
+ * Tokenizes the text and, if the language is known, removes any stop words and
+ * reduces the remaining words down to their stems.
+ */
+pub fn analyze(
+    text : &str,
+    language : Option<SupportedLanguage>
+) -> HashSet<String> {
+    tokenize(text).into_iter()
+        .filter_map(|word| {
+            match language {
+                Some(language) => {
+                    if language.is_stop_word(&word) {
+                        None
+                    } else {
+                        Some(language.stem(&word))
+                    }
+                },
+                None => Some(word)
+            }
+        }).collect()
+}
+
+/**
+ * Detects which language the user's query is written in, if the detector is
+ * confident enough and words in that language can be stemmed.  Queries are short,
+ * so this often isn't known.
+ */
+pub fn detect_query_language(
+    query : &str
+) -> Option<SupportedLanguage> {
+    match whatlang::detect(query) {
+        Some(info) if info.is_reliable() => SupportedLanguage::from_code(get_iso_639_1(info.lang())),
+        _ => None
+    }
+}
+
+/**
+ * Analyzes the user's query so that it can be matched against the index.
+ *
+ * Stop words of the given languages are removed, as are words of less than three
+ * characters, and each word is expanded to its stem in each of those languages,
+ * along with the word itself for posts that weren't stemmed.  Without any
+ * languages, the words are left as they are.
+ *
+ * Words can be limited to a single field by prefixing them with the field's name,
+ * for example `title:rust`.
+ */
+pub fn analyze_query(
+    query : &str,
+    languages : &[SupportedLanguage]
+) -> Vec<QueryTerm> {
+    let mut seen = HashSet::<String>::new();
+    query.split_whitespace()
+        .flat_map(|chunk| {
+            let (fields, text) = match chunk.split_once(':') {
+                Some((prefix, text)) => match IndexField::from_prefix(&prefix.to_lowercase()) {
+                    Some(field) => (field.get_flag(), text),
+                    None => (IndexField::get_all_flags(), chunk)
+                },
+                None => (IndexField::get_all_flags(), chunk)
+            };
+            tokenize(text).into_iter().map(move |word| {
+                (word, fields)
+            })
+        })
+        .filter(|(word, _)| {
+            word.len() > 2
+        })
+        .filter(|(word, _)| {
+            !languages.iter().any(|language| {
+                language.is_stop_word(word)
+            })
+        })
+        .filter(|(word, _)| {
+            seen.insert(word.clone())
+        })
+        .map(|(word, fields)| {
+            let mut variants = languages.iter().map(|language| {
+                language.stem(&word)
+            }).collect::<HashSet<_>>();
+            variants.insert(word.clone());
+
+            QueryTerm {
+                term : word,
+                variants,
+                fields
+            }
+        }).collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashSet;
+    use crate::api::lemmy::models::post::Post;
+    use super::{
+        analyze_query,
+        detect_query_language,
+        extract_markdown,
+        get_domain,
+        normalize_url,
+        IndexField,
+        QueryTerm,
+        SupportedLanguage
+    };
+
+    /**
+     * A term that only matches the word itself, for testing what is done with
+     * the terms once the query has been analyzed.
+     */
+    pub(crate) fn query_term(
+        word : &str,
+        fields : i16
+    ) -> QueryTerm {
+        QueryTerm {
+            term : word.to_string(),
+            variants : HashSet::from([word.to_string()]),
+            fields
+        }
+    }
+
+    #[test]
+    fn analyzes_the_query_in_the_given_language() {
+        let query_terms = analyze_query("the running of the elections", &[SupportedLanguage::English]);
+
+        let terms = query_terms.iter().map(|query_term| {
+            query_term.term.as_str()
+        }).collect::<Vec<_>>();
+        assert_eq!(terms, vec!["running", "elections"]);
+
+        assert_eq!(query_terms[0].variants, HashSet::from(["running".to_string(), "run".to_string()]));
+        assert_eq!(query_terms[1].variants, HashSet::from(["elections".to_string(), "elect".to_string()]));
+    }
+
+    #[test]
+    fn leaves_words_alone_without_a_language() {
+        let query_terms = analyze_query("the running of the elections", &[]);
+
+        let terms = query_terms.iter().map(|query_term| {
+            query_term.term.as_str()
+        }).collect::<Vec<_>>();
+        assert_eq!(terms, vec!["the", "running", "elections"]);
+        assert_eq!(query_terms[1].variants, HashSet::from(["running".to_string()]));
+    }
+
+    #[test]
+    fn drops_short_and_repeated_words() {
+        let query_terms = analyze_query("go to rust rust", &[]);
+
+        assert_eq!(query_terms.len(), 1);
+        assert_eq!(query_terms[0].term, "rust");
+    }
+
+    #[test]
+    fn limits_words_to_a_field() {
+        let query_terms = analyze_query("title:rust release", &[]);
+
+        assert_eq!(query_terms[0].term, "rust");
+        assert_eq!(query_terms[0].fields, IndexField::Title.get_flag());
+        assert_eq!(query_terms[1].fields, IndexField::get_all_flags());
+    }
+
+    #[test]
+    fn detects_the_language_of_a_query() {
+        assert_eq!(
+            detect_query_language("Wie kann ich die neueste Version von Rust auf meinem Rechner installieren?"),
+            Some(SupportedLanguage::German)
+        );
+        assert_eq!(detect_query_language("rust"), None);
+    }
+
+    #[test]
+    fn detects_the_language_of_undetermined_posts() {
+        let mut post = Post {
+            name : "Die neue Version ist da".to_string(),
+            body : Some("Heute wurde die neue Version veröffentlicht, mit vielen Verbesserungen für alle Benutzer.".to_string()),
+            ..Default::default()
+        };
+        post.detect_language();
+
+        assert_eq!(post.detected_language.as_deref(), Some("de"));
+        assert!(post.language_confidence.is_some());
+    }
+
+    #[test]
+    fn keeps_the_authors_language() {
+        let mut post = Post {
+            name : "Die neue Version ist da".to_string(),
+            body : Some("Heute wurde die neue Version veröffentlicht, mit vielen Verbesserungen für alle Benutzer.".to_string()),
+            language : Some("en".to_string()),
+            ..Default::default()
+        };
+        post.detect_language();
+
+        assert_eq!(post.detected_language, None);
+        assert_eq!(post.language_confidence, None);
+    }
+
+    #[test]
+    fn discards_unreliable_detections() {
+        let mut post = Post {
+            name : "ok".to_string(),
+            detected_language : Some("en".to_string()),
+            language_confidence : Some(1.0),
+            ..Default::default()
+        };
+        post.detect_language();
+
+        assert_eq!(post.detected_language, None);
+        assert_eq!(post.language_confidence, None);
+    }
+
+    #[test]
+    fn extracts_the_text_that_a_reader_would_see() {
+        let markdown = extract_markdown("# Release\n\nThe **new** [release](https://example.com/release) is out, see https://example.org/notes for more\n\n![logo](https://example.com/logo.png)");
+
+        let words = markdown.text.split_whitespace().collect::<Vec<_>>();
+        assert_eq!(words, vec!["Release", "The", "new", "release", "is", "out,", "see", "for", "more", "logo"]);
+        assert_eq!(markdown.links, vec![
+            "https://example.com/release".to_string(),
+            "https://example.org/notes".to_string(),
+            "https://example.com/logo.png".to_string()
+        ]);
+        assert!(markdown.code.is_empty());
+    }
+
+    #[test]
+    fn keeps_code_apart_from_the_text() {
+        let markdown = extract_markdown("Call `foo_bar()` like so:\n\n```\nlet x = foo_bar();\n```");
+
+        assert!(!markdown.text.contains("foo_bar"));
+        assert!(markdown.text.contains("Call"));
+        assert!(markdown.code.contains("foo_bar()"));
+        assert!(markdown.code.contains("let x = foo_bar();"));
+    }
+
+    #[test]
+    fn keeps_the_contents_of_spoilers() {
+        let markdown = extract_markdown("::: spoiler Ending\nThe butler did it\n:::");
+
+        assert!(!markdown.text.contains(":::"));
+        assert!(!markdown.text.contains("spoiler"));
+        assert!(markdown.text.contains("Ending"));
+        assert!(markdown.text.contains("The butler did it"));
+    }
+
+    #[test]
+    fn normalizes_urls_to_the_same_page() {
+        for url in [
+            "https://www.example.com/page",
+            "http://example.com/page/",
+            "https://EXAMPLE.com/page#section",
+            "www.example.com/page"
+        ] {
+            assert_eq!(normalize_url(url).as_deref(), Some("example.com/page"), "{}", url);
+        }
+        assert_eq!(normalize_url("https://example.com/watch?v=1").as_deref(), Some("example.com/watch?v=1"));
+        assert_eq!(normalize_url("https://example.com/").as_deref(), Some("example.com"));
+        assert_eq!(normalize_url("not a url"), None);
+    }
+
+    #[test]
+    fn gets_the_domain_of_a_url() {
+        assert_eq!(get_domain("https://www.Example.com/page").as_deref(), Some("example.com"));
+        assert_eq!(get_domain("https://blog.example.com/").as_deref(), Some("blog.example.com"));
+        assert_eq!(get_domain("example.com"), None);
     }
 }
@@ -1,7 +1,9 @@
+use std::collections::HashMap;
 use async_recursion::async_recursion;
 use reqwest::Client;
 use crate::{
-    config,
+    config::Config,
+    crawler::notifier::SavedSearchNotifier,
     error::{
         Result,
         LogError, 
@@ -9,11 +11,15 @@ use crate::{
     },
     api::lemmy::{
         fetcher::Fetcher, 
-        models::post::PostData
+        models::{
+            post::PostData,
+            community::Community
+        }
     }, 
     database::{  
         dbo::{
             site::SiteDBO,
+            language::LanguageDBO,
             crawler::CrawlerDatabase
         }, 
         DatabasePool, 
@@ -27,7 +33,7 @@ use crate::{
 pub struct Crawler {
     pub instance : String,
 
-    config : config::Crawler,
+    config : Config,
     pool : DatabasePool,
     fetcher : Fetcher,
 
@@ -44,7 +50,7 @@ impl Crawler {
 
     pub fn new(
         instance : String,
-        config : config::Crawler,
+        config : Config,
         pool : DatabasePool,
 
         just_update_remote_ids : bool
@@ -63,6 +69,12 @@ impl Crawler {
         })
     }
 
+    /**
+     * Crawls the instance, then sends any new posts to the saved searches that they
+     * match.  Any federated instances are only crawled for the ids that they know
+     * each post by, which is why the saved searches wait until they're done.  The
+     * language of every instance is worked out from its posts at the same time.
+     */
     #[async_recursion]
     pub async fn crawl(
         &self
@@ -72,10 +84,32 @@ impl Crawler {
             return Err(LemmySearchError::Generic("Crawling disabled by robots.txt"));
         }
 
-        let site_view = self.fetcher.fetch_site_data()
+        let site_response = self.fetcher.fetch_site_data()
+            .await
+            .log_error(format!("\t...unable to fetch site data for instance '{}'.", self.instance).as_str(), self.config.crawler.log)?;
+
+        let site_view = site_response.site_view;
+
+        let all_languages = site_response.all_languages
+            .unwrap_or_default();
+
+        LanguageDBO::new(self.pool.clone())
+            .upsert_all(all_languages.clone())
             .await
-            .log_error(format!("\t...unable to fetch site data for instance '{}'.", self.instance).as_str(), self.config.log)
-            ?.site_view;
+            .log_error("\t...failed to update languages during crawl.", self.config.crawler.log)?;
+
+        // Lemmy only sends the language's id with each post, so keep track of
+        // what code each id refers to on this instance.
+        // Lemmy uses 'und' for any post whose author didn't pick a language,
+        // those posts are left for the analyzer to detect instead.
+        let languages = all_languages
+            .into_iter()
+            .filter(|language| {
+                language.code != "und"
+            })
+            .map(|language| {
+                (language.id, language.code)
+            }).collect::<HashMap<_, _>>();
 
         let site_actor_id = site_view.site.actor_id.clone();
 
@@ -83,7 +117,7 @@ impl Crawler {
 
         if !site_dbo.upsert(site_view.clone())
             .await
-            .log_error(format!("\t...error during update {} during crawl.", Site::get_table_name()).as_str(), self.config.log)? {
+            .log_error(format!("\t...error during update {} during crawl.", Site::get_table_name()).as_str(), self.config.crawler.log)? {
                 println!("\t...failed to update {} during crawl.", Site::get_table_name());
             }
 
@@ -91,16 +125,25 @@ impl Crawler {
             self.fetch_remote_ids(&site_actor_id)
                 .await?;
         } else {
-            self.fetch_posts(&site_actor_id)
+            self.fetch_posts(&site_actor_id, &languages)
                 .await?;
 
-            if !self.config.single_instance_only.unwrap_or(false) {
+            // A community's subscribers and activity can't be seen from its posts,
+            // missing them shouldn't stop the rest of the crawl though.
+            let _ = self.fetch_communities()
+                .await
+                .log_error("\t...failed to fetch the list of communities.", self.config.crawler.log);
+
+            if !self.config.crawler.single_instance_only.unwrap_or(false) {
                 let federated_instances = self.fetcher.fetch_instances()
                 .await?
                 .federated_instances
                 .linked;
     
                 for instance in federated_instances {
+                    if !self.config.crawler.is_instance_allowed(&instance.domain) {
+                        continue;
+                    }
                     if match instance.software {
                         Some(value) => value == "lemmy",
                         None => false
@@ -119,6 +162,13 @@ impl Crawler {
                     }
                 }
             }
+
+            let _ = site_dbo.update_languages()
+                .await
+                .log_error("\t...failed to update the languages of each instance.", self.config.crawler.log);
+
+            SavedSearchNotifier::notify_new_posts(self.config.clone(), self.pool.clone())
+                .await;
         }
 
         println!("\t...done.");
@@ -128,7 +178,8 @@ impl Crawler {
 
     async fn fetch_posts(
         &self,
-        site_actor_id : &str
+        site_actor_id : &str,
+        languages : &HashMap<i32, String>
     ) -> Result<()> {
 
         let site_dbo = SiteDBO::new(self.pool.clone());
@@ -141,7 +192,7 @@ impl Crawler {
         loop {
             let posts = match self.fetcher.fetch_posts(page+1)
                 .await
-                .log_error(format!("\tfailed to fetch another page of {}...", PostData::get_table_name()).as_str(), self.config.log) {
+                .log_error(format!("\tfailed to fetch another page of {}...", PostData::get_table_name()).as_str(), self.config.crawler.log) {
                     Ok(value) => value,
                     Err(_) => continue
                 };
@@ -154,6 +205,12 @@ impl Crawler {
 
             let filtered_posts = posts.into_iter().filter(|post_data| {
                 !post_data.post.deleted.unwrap_or(false) && !post_data.post.removed.unwrap_or(false)
+            }).map(|mut post_data| {
+                post_data.post.language = languages.get(&post_data.post.language_id)
+                    .cloned();
+                post_data.post.detect_language();
+                post_data.post.normalize_url();
+                post_data
             }).collect::<Vec<_>>();
 
             let filtered_count = filtered_posts.len();
@@ -161,7 +218,7 @@ impl Crawler {
             let pool = self.pool.clone();
             let site_actor_id_string = site_actor_id.to_string();
 
-            let mut crawler_database = CrawlerDatabase::init(pool.clone()).await?;
+            let mut crawler_database = CrawlerDatabase::init(pool.clone(), self.config.crawler.index_code).await?;
 
             crawler_database.bulk_update_post(&site_actor_id_string, &filtered_posts)
                 .await
@@ -181,6 +238,37 @@ impl Crawler {
         Ok(())
     }
 
+    async fn fetch_communities(
+        &self
+    ) -> Result<()> {
+
+        let mut crawler_database = CrawlerDatabase::init(self.pool.clone(), self.config.crawler.index_code).await?;
+
+        let mut total_found = 0;
+        let mut page = 0;
+        loop {
+            let communities = self.fetcher.fetch_communities(page+1)
+                .await?;
+
+            if communities.is_empty() {
+                break;
+            }
+
+            println!("\tfetched another {} {}...", communities.len(), Community::get_table_name());
+
+            crawler_database.bulk_update_communities(&communities)
+                .await?;
+
+            total_found += communities.len();
+
+            println!("\tupdated {} {}...", total_found, Community::get_table_name());
+
+            page += 1;
+        }
+
+        Ok(())
+    }
+
     async fn fetch_remote_ids(
         &self,
         site_actor_id : &str
@@ -191,13 +279,13 @@ impl Crawler {
         let last_page = site_dbo.get_last_post_page(site_actor_id)
             .await?;
 
-        let mut crawler_database = CrawlerDatabase::init(self.pool.clone()).await?;
+        let mut crawler_database = CrawlerDatabase::init(self.pool.clone(), self.config.crawler.index_code).await?;
 
         let mut page = last_page;
         loop {
             let posts = self.fetcher.fetch_posts(page+1)
                 .await
-                .log_error("\tfailed to fetch another page of 'post ids'...", self.config.log)?;
+                .log_error("\tfailed to fetch another page of 'post ids'...", self.config.crawler.log)?;
 
             if posts.is_empty() {
                 break;
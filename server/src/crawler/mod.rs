@@ -1,12 +1,16 @@
 pub mod analyzer;
 pub mod crawler;
+pub mod notifier;
 
 use self::crawler::Crawler;
 use std::{time::Duration, path::Path};
 use async_std::fs::remove_file;
 use tokio::task::JoinHandle;
 use crate::{
-    config, 
+    config::{
+        Config, 
+        handle::ConfigHandle
+    }, 
     database::Database,
     error::{
         LogError,
@@ -19,14 +23,14 @@ use clokwerk::{
 };
 
 pub struct Runner {
-    config : config::Crawler,
+    config : ConfigHandle,
     handle : Option<JoinHandle<()>>,
     database : Database
 }
 
 impl Runner {
     pub fn new(
-        config : &config::Crawler,
+        config : &ConfigHandle,
         database : Database
     ) -> Self {
         Self { 
@@ -36,30 +40,57 @@ impl Runner {
         }
     }
 
+    /**
+     * Starts the scheduler.  The crawler's config is re-read every time a job
+     * runs, and should the interval change the scheduler is rebuilt so that
+     * config reloads are picked up without having to restart.
+     */
     pub fn start(&mut self) {
         self.stop();
 
-        let mut scheduler = AsyncScheduler::with_tz(chrono::Utc);
+        let config = self.config.clone();
+        let database = self.database.clone();
 
-        let config1 = self.config.clone();
-        let database1 = self.database.clone();
-        let config2 = self.config.clone();
-        let database2 = self.database.clone();
+        self.handle = Some(tokio::spawn(async move {
+            let mut interval_hours = config.get().crawler.interval_hours;
+            let mut scheduler = Self::create_scheduler(&config, &database, interval_hours);
 
-        scheduler.every(6.hours())            
-            .run(move || Self::run_regular(config1.clone(), database1.clone()));
+            loop {
+                let current_interval_hours = config.get().crawler.interval_hours;
+                if current_interval_hours != interval_hours {
+                    println!("Crawler will now run every {} hours...", current_interval_hours);
 
-        scheduler.every(1.minutes())
-            .run(move || Self::manual_check(config2.clone(), database2.clone()));
+                    interval_hours = current_interval_hours;
+                    scheduler = Self::create_scheduler(&config, &database, interval_hours);
+                }
 
-        self.handle = Some(tokio::spawn(async move {
-            loop {
                 scheduler.run_pending().await;
                 tokio::time::sleep(Duration::from_secs(1)).await;
             }
         }));
     }
 
+    fn create_scheduler(
+        config : &ConfigHandle,
+        database : &Database,
+        interval_hours : u32
+    ) -> AsyncScheduler<chrono::Utc> {
+        let mut scheduler = AsyncScheduler::with_tz(chrono::Utc);
+
+        let config1 = config.clone();
+        let database1 = database.clone();
+        let config2 = config.clone();
+        let database2 = database.clone();
+
+        scheduler.every(interval_hours.max(1).hours())
+            .run(move || Self::run_regular(config1.get(), database1.clone()));
+
+        scheduler.every(1.minutes())
+            .run(move || Self::manual_check(config2.get(), database2.clone()));
+
+        scheduler
+    }
+
     pub fn stop(&mut self) {
         match &self.handle {
             Some(value) => value.abort(),
@@ -69,7 +100,7 @@ impl Runner {
     }
 
     async fn manual_check(
-        config : config::Crawler,
+        config : Config,
         database : Database
     ) {
         let file = Path::new("/lemmy/config/crawl");
@@ -82,17 +113,17 @@ impl Runner {
                     },
                     Err(err) => {
                         let _ = Result::<()>::Err(LemmySearchError::from(err))
-                            .log_error("Failed to delete manual crawl trigger.", config.log);
+                            .log_error("Failed to delete manual crawl trigger.", config.crawler.log);
                     }
                 }
         }
     }
 
     async fn run_regular(
-        config : config::Crawler,
+        config : Config,
         database : Database
     ) {
-        if config.enabled {
+        if config.crawler.enabled {
             Self::run(config, database)
                 .await;
         } else {
@@ -101,15 +132,15 @@ impl Runner {
     }
 
     async fn run(
-        config : config::Crawler,
+        config : Config,
         database : Database
     ) {
-        println!("Crawler is starting to index '{}'...", config.seed_instance);
-            let _ = Crawler::new(config.seed_instance.clone(), config.clone(), database.pool, false)
+        println!("Crawler is starting to index '{}'...", config.crawler.seed_instance);
+            let _ = Crawler::new(config.crawler.seed_instance.clone(), config.clone(), database.pool, false)
                     .unwrap()
                     .crawl()
                     .await
-                    .log_error(format!("The crawler for '{}' encountered an error.", config.seed_instance).as_str(), config.log);
+                    .log_error(format!("The crawler for '{}' encountered an error.", config.crawler.seed_instance).as_str(), config.crawler.log);
 
             println!("Crawling complete.");
     }
@@ -0,0 +1,464 @@
+use std::{
+    net::{
+        IpAddr,
+        SocketAddr
+    },
+    time::Duration
+};
+use chrono::Utc;
+use reqwest::{
+    Client,
+    RequestBuilder,
+    redirect::Policy
+};
+use tokio::net::lookup_host;
+use url::{
+    Host,
+    Url
+};
+use serde::{
+    Serialize,
+    Deserialize
+};
+use uuid::Uuid;
+use crate::{
+    api::{
+        opensearch::OpenSearchHandler,
+        search::{
+            SearchHandler,
+            models::search::{
+                SearchQuery,
+                SearchResult,
+                SearchSort
+            }
+        },
+        v1::models::Post
+    },
+    config::{
+        Config,
+        SavedSearches
+    },
+    database::{
+        DatabasePool,
+        dbo::saved_search::SavedSearchDBO,
+        schema::{
+            new_post::NewPost,
+            saved_search::SavedSearch
+        }
+    },
+    error::{
+        LemmySearchError,
+        LogError,
+        Result
+    }
+};
+
+/**
+ * What is sent to a plain webhook.  The posts use the same models as `/api/v1/`.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedSearchNotification {
+    pub id : Uuid,
+    pub query : String,
+    /// How many new posts matched, which may be more than were sent, in which case
+    /// the rest are sent after the next crawl.
+    pub total_results : i32,
+    pub posts : Vec<SavedSearchMatch>
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedSearchMatch {
+    /// Where to view the post on the preferred instance.
+    pub post_url : String,
+    #[serde(flatten)]
+    pub post : Post
+}
+
+/**
+ * The body that matrix-hookshot's generic webhooks expect.
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct MatrixMessage {
+    text : String,
+    html : String
+}
+
+/**
+ * Checks every saved search against the posts that were recently indexed, and
+ * sends any that match to the search's webhook.  A new post can only be matched
+ * once it has an id on the search's preferred instance, which may not be until a
+ * later crawl, so new posts keep being checked for `NEW_POST_DAYS` and each search
+ * remembers which of them it has already been sent.
+ */
+pub struct SavedSearchNotifier {
+    config : Config,
+    pool : DatabasePool
+}
+
+impl SavedSearchNotifier {
+
+    const MAX_POSTS : i32 = 20;
+    const NEW_POST_DAYS : i64 = 7;
+    const TIMEOUT : Duration = Duration::from_secs(10);
+
+    pub fn new(
+        config : Config,
+        pool : DatabasePool
+    ) -> Self {
+        Self {
+            config,
+            pool
+        }
+    }
+
+    /**
+     * Notifies every saved search of the new posts that it hasn't been sent yet.
+     * The crawl itself has already succeeded by now, so any errors are only logged.
+     */
+    pub async fn notify_new_posts(
+        config : Config,
+        pool : DatabasePool
+    ) {
+        let log = config.crawler.log;
+        let _ = Self::new(config, pool)
+            .notify()
+            .await
+            .log_error("Failed to check the saved searches for new posts.", log);
+    }
+
+    /**
+     * A webhook that fails is logged and skipped, so that it can't stop any of the
+     * others from being sent, and its posts are tried again after the next crawl.
+     */
+    pub async fn notify(
+        &self
+    ) -> Result<()> {
+        let saved_search_dbo = SavedSearchDBO::new(self.pool.clone());
+
+        // New posts are kept even while saved searches are disabled, so they have
+        // to be cleared out either way.
+        saved_search_dbo.remove_new_posts(Utc::now() - chrono::Duration::days(Self::NEW_POST_DAYS))
+            .await?;
+
+        if !self.config.saved_searches.enabled {
+            return Ok(());
+        }
+
+        let new_posts = saved_search_dbo.retrieve_new_posts()
+            .await?;
+        if new_posts.is_empty() {
+            return Ok(());
+        }
+
+        let saved_searches = saved_search_dbo.retrieve_all()
+            .await?;
+
+        println!("Checking {} saved searches against {} new posts...", saved_searches.len(), new_posts.len());
+
+        for saved_search in saved_searches {
+            let _ = self.notify_saved_search(&saved_search, &new_posts)
+                .await
+                .log_error(format!("\t...failed to notify saved search '{}'.", saved_search.id).as_str(), self.config.crawler.log);
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Only posts indexed after the search was saved are sent.  Cross-posts aren't
+     * collapsed, as each of them has to be remembered as sent.  Any more than
+     * `MAX_POSTS` are left for the next crawl.
+     */
+    async fn notify_saved_search(
+        &self,
+        saved_search : &SavedSearch,
+        new_posts : &[NewPost]
+    ) -> Result<()> {
+        let saved_search_dbo = SavedSearchDBO::new(self.pool.clone());
+
+        let sent_posts = saved_search_dbo.retrieve_sent_posts(saved_search.id)
+            .await?;
+
+        let unsent_posts = new_posts.iter()
+            .filter(|new_post| {
+                new_post.indexed >= saved_search.created && !sent_posts.contains(&new_post.post_actor_id)
+            })
+            .map(|new_post| {
+                new_post.post_actor_id.clone()
+            })
+            .collect::<Vec<_>>();
+        if unsent_posts.is_empty() {
+            return Ok(());
+        }
+
+        let search_query = SearchQuery {
+            query : saved_search.query.clone(),
+            preferred_instance : saved_search.preferred_instance.clone(),
+            sort : Some(SearchSort::New),
+            collapse : Some(false),
+            page_size : Some(Self::MAX_POSTS),
+            posts : Some(unsent_posts),
+            ..Default::default()
+        };
+
+        let SearchResult { posts, total_results, .. } = SearchHandler::query_posts(self.pool.clone(), &self.config, &search_query)
+            .await
+            .map_err(|err| {
+                LemmySearchError::Unknown(err.to_string())
+            })?;
+
+        if posts.is_empty() {
+            return Ok(());
+        }
+
+        println!("\tSending {} new matches for '{}'...", total_results, saved_search.query);
+
+        // The preferred instance is stored without the https://, re-add it back.
+        let preferred_instance_actor_id = format!("https://{}/", saved_search.preferred_instance);
+
+        let post_actor_ids = posts.iter().map(|post| {
+            post.ap_id.clone()
+        }).collect::<Vec<_>>();
+
+        let notification = SavedSearchNotification {
+            id : saved_search.id,
+            query : saved_search.query.clone(),
+            total_results,
+            posts : posts.into_iter().map(|post| {
+                SavedSearchMatch {
+                    post_url : format!("{}post/{}", preferred_instance_actor_id, post.remote_id),
+                    post : post.into()
+                }
+            }).collect()
+        };
+
+        let client = Self::get_client(&self.config.saved_searches, &saved_search.webhook_url)
+            .await?;
+
+        Self::get_request(&client, saved_search, &notification)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        saved_search_dbo.add_sent_posts(saved_search.id, &post_actor_ids)
+            .await?;
+        saved_search_dbo.set_last_notified(saved_search.id)
+            .await?;
+
+        Ok(())
+    }
+
+    /**
+     * Builds the request in the format that the webhook expects, which is either
+     * JSON for any webhook, a plain text message for ntfy or a hookshot message
+     * for Matrix.
+     */
+    fn get_request(
+        client : &Client,
+        saved_search : &SavedSearch,
+        notification : &SavedSearchNotification
+    ) -> RequestBuilder {
+        let title = if notification.total_results == 1 {
+            format!("1 new post for '{}'", notification.query)
+        } else {
+            format!("{} new posts for '{}'", notification.total_results, notification.query)
+        };
+
+        let request = client.post(&saved_search.webhook_url);
+
+        match saved_search.format.as_str() {
+            "ntfy" => {
+                let message = notification.posts.iter().map(|post_match| {
+                    format!("{}\n{}", post_match.post.name, post_match.post_url)
+                }).collect::<Vec<_>>()
+                    .join("\n\n");
+
+                // ntfy also takes these as headers, but those can't hold the
+                // non-ASCII characters that a query might have.
+                let mut options = vec![
+                    ("title", title),
+                    ("tags", "mag".to_string())
+                ];
+                if let Some(post_match) = notification.posts.first() {
+                    options.push(("click", post_match.post_url.clone()));
+                }
+                request.query(&options)
+                    .body(message)
+            },
+            "matrix" => {
+                let text = notification.posts.iter().map(|post_match| {
+                    format!("* {} {}", post_match.post.name, post_match.post_url)
+                }).collect::<Vec<_>>()
+                    .join("\n");
+                let html = notification.posts.iter().map(|post_match| {
+                    format!("<li><a href=\"{}\">{}</a></li>",
+                        OpenSearchHandler::escape_xml(&post_match.post_url),
+                        OpenSearchHandler::escape_xml(&post_match.post.name)
+                    )
+                }).collect::<String>();
+
+                request.json(&MatrixMessage {
+                    text : format!("{}\n{}", title, text),
+                    html : format!("<p>{}</p><ul>{}</ul>", OpenSearchHandler::escape_xml(&title), html)
+                })
+            },
+            _ => request.json(notification)
+        }
+    }
+
+    /**
+     * Checks that a webhook can be sent to, returning the addresses that its host
+     * resolved to.  Unless the host is on the allow list, every one of those must
+     * be a public address, so that webhooks can't reach the server's own network.
+     */
+    pub async fn resolve_webhook(
+        config : &SavedSearches,
+        webhook_url : &str
+    ) -> Result<(String, Vec<SocketAddr>)> {
+        let url = Url::parse(webhook_url).map_err(|_| {
+            LemmySearchError::Generic("The webhook isn't a valid url.")
+        })?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(LemmySearchError::Generic("The webhook must be an http or https url."));
+        }
+        let host = url.host_str().ok_or(LemmySearchError::Generic("The webhook has no host."))?
+            .to_lowercase();
+        let port = url.port_or_known_default().unwrap_or(80);
+
+        let is_allowed = match &config.allowed_webhook_hosts {
+            Some(allowed) if !allowed.is_empty() => {
+                if !allowed.iter().any(|allowed| allowed.to_lowercase() == host) {
+                    return Err(LemmySearchError::Generic("The webhook's host isn't allowed."));
+                }
+                true
+            },
+            _ => false
+        };
+
+        let addresses = match url.host() {
+            Some(Host::Ipv4(ip)) => vec![SocketAddr::new(IpAddr::V4(ip), port)],
+            Some(Host::Ipv6(ip)) => vec![SocketAddr::new(IpAddr::V6(ip), port)],
+            _ => lookup_host((host.as_str(), port))
+                .await
+                .map_err(|_| {
+                    LemmySearchError::Generic("The webhook's host couldn't be found.")
+                })?
+                .collect()
+        };
+
+        if addresses.is_empty() {
+            return Err(LemmySearchError::Generic("The webhook's host couldn't be found."));
+        }
+        if !is_allowed && !addresses.iter().all(|address| Self::is_public_address(address.ip())) {
+            return Err(LemmySearchError::Generic("The webhook's host isn't a public address."));
+        }
+
+        Ok((host, addresses))
+    }
+
+    /**
+     * Whether the address can be reached from the internet, rather than being a
+     * loopback, private, link-local or otherwise reserved address.
+     */
+    pub fn is_public_address(
+        ip : IpAddr
+    ) -> bool {
+        match ip {
+            IpAddr::V4(ip) => {
+                let octets = ip.octets();
+                !(ip.is_private()
+                    || ip.is_loopback()
+                    || ip.is_link_local()
+                    || ip.is_unspecified()
+                    || ip.is_broadcast()
+                    || ip.is_multicast()
+                    || ip.is_documentation()
+                    || octets[0] == 0
+                    // Shared address space, used by carrier-grade NAT.
+                    || (octets[0] == 100 && (octets[1] & 0xc0) == 64))
+            },
+            IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+                Some(ip) => Self::is_public_address(IpAddr::V4(ip)),
+                None => {
+                    let first = ip.segments()[0];
+                    !(ip.is_loopback()
+                        || ip.is_unspecified()
+                        || ip.is_multicast()
+                        // Unique local addresses.
+                        || (first & 0xfe00) == 0xfc00
+                        // Link-local and the old site-local addresses.
+                        || (first & 0xffc0) == 0xfe80
+                        || (first & 0xffc0) == 0xfec0)
+                }
+            }
+        }
+    }
+
+    /**
+     * A client that can only connect to the addresses that the webhook was checked
+     * against, so that its host can't resolve to a different address by the time
+     * that it's sent, and that won't follow redirects anywhere else.
+     */
+    async fn get_client(
+        config : &SavedSearches,
+        webhook_url : &str
+    ) -> Result<Client> {
+        let (host, addresses) = Self::resolve_webhook(config, webhook_url)
+            .await?;
+
+        Ok(Client::builder()
+            .timeout(Self::TIMEOUT)
+            .redirect(Policy::none())
+            .resolve_to_addrs(&host, &addresses)
+            .build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+    use crate::config::SavedSearches;
+    use super::SavedSearchNotifier;
+
+    fn is_public(
+        ip : &str
+    ) -> bool {
+        SavedSearchNotifier::is_public_address(ip.parse::<IpAddr>().unwrap())
+    }
+
+    #[test]
+    fn rejects_internal_addresses() {
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "0.0.0.0", "100.64.0.1", "::1", "::", "fe80::1", "fd00::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public(ip), "{} should not be public", ip);
+        }
+    }
+
+    #[test]
+    fn accepts_public_addresses() {
+        for ip in ["1.1.1.1", "93.184.216.34", "2606:4700:4700::1111", "::ffff:1.1.1.1"] {
+            assert!(is_public(ip), "{} should be public", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_webhooks_to_internal_hosts() {
+        let config = SavedSearches {
+            enabled : true,
+            allowed_webhook_hosts : None
+        };
+        for url in ["http://127.0.0.1:5432/", "http://169.254.169.254/latest/meta-data", "http://[::1]/", "http://localhost/", "ftp://1.1.1.1/"] {
+            assert!(SavedSearchNotifier::resolve_webhook(&config, url).await.is_err(), "{} should be rejected", url);
+        }
+        assert!(SavedSearchNotifier::resolve_webhook(&config, "https://1.1.1.1/hook").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn only_allows_listed_hosts() {
+        let config = SavedSearches {
+            enabled : true,
+            allowed_webhook_hosts : Some(vec!["192.168.1.10".to_string()])
+        };
+        assert!(SavedSearchNotifier::resolve_webhook(&config, "http://192.168.1.10/hook").await.is_ok());
+        assert!(SavedSearchNotifier::resolve_webhook(&config, "https://1.1.1.1/hook").await.is_err());
+    }
+}
@@ -1,43 +1,106 @@
 use std::{
-    collections::HashSet, 
+    collections::{
+        HashMap,
+        HashSet
+    }, 
     fmt::Debug,
 };
+use chrono::Utc;
 use deadpool::managed::Object;
 use deadpool_r2d2::Manager;
 use postgres::NoTls;
 use r2d2_postgres::PostgresConnectionManager;
 
+use super::update_post_counts;
 use crate::{
     database::{
         DatabasePool, 
         schema::{
             DatabaseSchema, 
             word::Word, 
-            xref::Search
+            xref::Search,
+            link::Link,
+            community_stats::CommunityStats,
+            new_post::NewPost
         }
     }, 
     error::Result,
     api::lemmy::models::{
         post::PostData, 
-        id::LemmyId, author::Author, community::Community,
+        id::LemmyId, author::Author, community::{
+            Community,
+            CommunityView
+        },
     }, 
-    crawler::analyzer::Analyzer
+    crawler::analyzer::{
+        Analyzer,
+        IndexField
+    }
 };
 
 pub struct CrawlerDatabase {
-    client : Object<Manager<PostgresConnectionManager<NoTls>>>
+    client : Object<Manager<PostgresConnectionManager<NoTls>>>,
+    index_code : bool
 }
 
 impl CrawlerDatabase {
 
-    pub async fn init(pool : DatabasePool) -> Result<Self> {
+    pub async fn init(
+        pool : DatabasePool, 
+        index_code : bool
+    ) -> Result<Self> {
         let client = pool.get().await?;
 
         Ok(Self {
-            client
+            client,
+            index_code
         })
     }
 
+    /**
+     * Gets the words to index for the post, leaving out any that only appear in
+     * code if code isn't being indexed.
+     */
+    fn get_words_for_post(
+        &self,
+        post_data : &PostData
+    ) -> HashMap<String, i16> {
+        let excluded = if self.index_code {
+            0
+        } else {
+            IndexField::Code.get_flag()
+        };
+
+        post_data.get_distinct_words()
+            .into_iter()
+            .map(|(word, fields)| {
+                (word, fields & !excluded)
+            })
+            .filter(|(_, fields)| {
+                *fields != 0
+            })
+            .collect()
+    }
+
+    fn get_links_for_post(
+        post_data : &PostData
+    ) -> HashSet<Link> {
+        post_data.get_links()
+            .into_iter()
+            .map(|url| {
+                Link {
+                    post_ap_id : post_data.post.ap_id.clone(),
+                    url
+                }
+            })
+            .collect()
+    }
+
+    /**
+     * Stores the posts and indexes their words.  Those that had never been seen
+     * before are also kept as new posts, for saved searches to be checked against,
+     * and are counted towards their community and author.
+     */
     pub async fn bulk_update_post(
         &mut self,
         instance_actor_id : &str,
@@ -49,6 +112,7 @@ impl CrawlerDatabase {
         let mut lemmy_ids = HashSet::<_>::new();
         let mut all_words = HashSet::<Word>::new();
         let mut xrefs = HashSet::<_>::new();
+        let mut links = HashSet::<_>::new();
 
         for post in posts {
             authors.insert(post.creator.clone());
@@ -56,12 +120,15 @@ impl CrawlerDatabase {
             lemmy_ids.insert(LemmyId {
                 post_remote_id : post.post.id.clone(),
                 post_actor_id : post.post.ap_id.clone(),
-                instance_actor_id : instance_actor_id.to_string()
+                instance_actor_id : instance_actor_id.to_string(),
+                community_remote_id : post.community.id,
+                creator_remote_id : post.creator.id
             });
-            let words = post.post.get_distinct_words().into_iter().map(|word| {
+            let words = self.get_words_for_post(post).into_keys().map(|word| {
                 Word::from(word)
             }).collect::<HashSet<_>>();
             all_words.extend(words);
+            links.extend(Self::get_links_for_post(post));
         }
 
         self.update_authors(&authors).await?;
@@ -72,6 +139,27 @@ impl CrawlerDatabase {
             p.clone()
         }).collect();
         
+        let existing_posts = self.get_existing_posts(posts).await?;
+        let indexed = Utc::now();
+        let new_posts = posts.iter()
+            .filter(|post| {
+                !existing_posts.contains(&post.post.ap_id)
+            })
+            .collect::<Vec<_>>();
+        let counted_actor_ids = new_posts.iter()
+            .flat_map(|post| {
+                [post.community.actor_id.clone(), post.creator.actor_id.clone()]
+            })
+            .collect::<HashSet<_>>();
+        let new_posts = new_posts.into_iter()
+            .map(|post| {
+                NewPost {
+                    post_actor_id : post.post.ap_id.clone(),
+                    indexed
+                }
+            })
+            .collect::<HashSet<_>>();
+
         self.update_words(&words).await?;
         self.update_posts(&posts2).await?;
 
@@ -81,6 +169,9 @@ impl CrawlerDatabase {
 
         self.update_lemmy_ids(&lemmy_ids).await?;
         self.update_xref(&xrefs).await?;
+        self.update_links(&links).await?;
+        self.update_new_posts(&new_posts).await?;
+        self.update_post_counts(&counted_actor_ids).await?;
 
         if xrefs.len() == 0 && words.len() != 0 {
             println!("WARNING NO xrefs were calculated for posts!.")
@@ -89,17 +180,135 @@ impl CrawlerDatabase {
         Ok(())
     }
 
+    /**
+     * Which of the posts have already been stored.
+     */
+    async fn get_existing_posts(
+        &mut self,
+        posts : &[PostData]
+    ) -> Result<HashSet<String>> {
+        let ap_ids = posts.iter().map(|post| {
+            post.post.ap_id.clone()
+        }).collect::<Vec<_>>();
+
+        Ok(self.client.interact(move |client| {
+            client.query("
+                SELECT ap_id FROM posts
+                    WHERE ap_id = any($1)
+            ", &[&ap_ids])
+                .map(|rows| {
+                    rows.iter().map(|row| {
+                        row.get::<_, String>(0)
+                    }).collect::<HashSet<_>>()
+                })
+        }).await??)
+    }
+
+    /**
+     * Stores the communities from the instance's list of communities, along with
+     * how many subscribers and active users each of them has.
+     */
+    pub async fn bulk_update_communities(
+        &mut self,
+        community_views : &[CommunityView]
+    ) -> Result<()> {
+
+        let communities = community_views.iter().map(|community_view| {
+            community_view.community.clone()
+        }).collect::<HashSet<_>>();
+        let community_stats = community_views.iter().map(|community_view| {
+            CommunityStats::from(community_view)
+        }).collect::<HashSet<_>>();
+
+        self.update_communities(&communities).await?;
+        self.update_community_stats(&community_stats).await?;
+
+        Ok(())
+    }
+
+    /**
+     * Rebuilds the words and cross-references for posts that are already stored,
+     * without touching the posts themselves.
+     */
+    /**
+     * Clears the index within a transaction on this connection, so that searches
+     * keep using the old index until the new one is committed, and a reindex
+     * that fails part of the way through leaves the old index in place.
+     */
+    pub async fn begin_reindex(
+        &mut self
+    ) -> Result<()> {
+        Ok(self.client.interact(move |client| {
+            client.batch_execute("
+                BEGIN;
+                DELETE FROM xref;
+                DELETE FROM links;
+                DELETE FROM words;
+            ")
+        }).await??)
+    }
+
+    pub async fn commit_reindex(
+        &mut self
+    ) -> Result<()> {
+        Ok(self.client.interact(move |client| {
+            client.batch_execute("COMMIT")
+        }).await??)
+    }
+
+    pub async fn rollback_reindex(
+        &mut self
+    ) -> Result<()> {
+        Ok(self.client.interact(move |client| {
+            client.batch_execute("ROLLBACK")
+        }).await??)
+    }
+
+    pub async fn bulk_reindex_post(
+        &mut self,
+        posts : &Vec<PostData>
+    ) -> Result<()> {
+
+        let words = posts.iter().flat_map(|post| {
+            self.get_words_for_post(post).into_keys()
+        }).map(|word| {
+            Word::from(word)
+        }).collect::<HashSet<_>>();
+
+        let links = posts.iter().flat_map(|post| {
+            Self::get_links_for_post(post)
+        }).collect::<HashSet<_>>();
+
+        self.update_links(&links).await?;
+
+        if words.is_empty() {
+            return Ok(());
+        }
+
+        self.update_words(&words).await?;
+
+        let mut xrefs = HashSet::<_>::new();
+        for post in posts {
+            xrefs.extend(self.get_xrefs_for_post(post).await?);
+        }
+
+        self.update_xref(&xrefs).await?;
+
+        Ok(())
+    }
+
     pub async fn get_xrefs_for_post(
         &mut self,
         post_data : &PostData
     ) -> Result<HashSet<Search>> {
 
-        let words = post_data.post.get_distinct_words()
-            .into_iter()
+        let fields = self.get_words_for_post(post_data);
+        let words = fields.keys()
+            .cloned()
             .collect::<Vec<_>>();
 
         let query = "
-            SELECT w.id, p.ap_id FROM posts AS p
+            SELECT w.id, p.ap_id, w.word FROM posts AS p
             JOIN words AS w ON w.id = w.id
             WHERE w.word = any($1)
                 AND p.ap_id = $2
@@ -111,9 +320,11 @@ impl CrawlerDatabase {
             client.query(&query, &[&words, &post_data.post.ap_id])
                 .map(|rows| {
                     rows.into_iter().map(|row| {
+                        let word : String = row.get(2);
                         Search {
                             word_id : row.get(0),
-                            post_ap_id : row.get(1)
+                            post_ap_id : row.get(1),
+                            fields : fields[&word]
                         }
                     }).collect::<HashSet<_>>()
                 })
@@ -132,7 +343,9 @@ impl CrawlerDatabase {
             lemmy_ids.insert(LemmyId {
                 post_remote_id : post.post.id.clone(),
                 post_actor_id : post.post.ap_id.clone(),
-                instance_actor_id : instance_actor_id.to_string()
+                instance_actor_id : instance_actor_id.to_string(),
+                community_remote_id : post.community.id,
+                creator_remote_id : post.creator.id
             });
         }
 
@@ -254,6 +467,26 @@ impl CrawlerDatabase {
         }).await??)
     }
 
+    async fn update_community_stats(
+        &mut self,        
+        objects : &HashSet<CommunityStats>
+    ) -> Result<u64> {
+        let objects = objects.clone();
+        
+        Ok(self.client.interact(move |client| {
+            let q = Self::bulk_get_query(&objects);
+
+            let params = objects.get_values();
+
+            match q {
+                Some(query) => {
+                    client.execute(&query, &params)
+                },
+                None => Ok(0)
+            }
+        }).await??)
+    }
+
     async fn update_posts(
         &mut self,
         objects : &HashSet<PostData>
@@ -294,12 +527,65 @@ impl CrawlerDatabase {
         }).await??)
     }
 
+    /**
+     * Posts can have thousands of cross-references between them, far more than can
+     * be sent as one parameter each, so they're sent as arrays instead.
+     */
     async fn update_xref(
         &mut self,
         objects : &HashSet<Search>
+    ) -> Result<u64> {
+        let word_ids = objects.iter().map(|xref| {
+            xref.word_id
+        }).collect::<Vec<_>>();
+        let post_ap_ids = objects.iter().map(|xref| {
+            xref.post_ap_id.clone()
+        }).collect::<Vec<_>>();
+        let fields = objects.iter().map(|xref| {
+            xref.fields
+        }).collect::<Vec<_>>();
+
+        Ok(self.client.interact(move |client| {
+            client.execute("
+                INSERT INTO xref (word_id, post_ap_id, fields)
+                    SELECT * FROM unnest($1::UUID[], $2::VARCHAR[], $3::INT2[])
+                ON CONFLICT (word_id, post_ap_id)
+                    DO UPDATE SET fields = excluded.fields
+                ",
+                &[&word_ids, &post_ap_ids, &fields]
+            )
+        }).await??)
+    }
+
+    async fn update_links(
+        &mut self,
+        objects : &HashSet<Link>
+    ) -> Result<u64> {
+        let post_ap_ids = objects.iter().map(|link| {
+            link.post_ap_id.clone()
+        }).collect::<Vec<_>>();
+        let urls = objects.iter().map(|link| {
+            link.url.clone()
+        }).collect::<Vec<_>>();
+
+        Ok(self.client.interact(move |client| {
+            client.execute("
+                INSERT INTO links (post_ap_id, url)
+                    SELECT * FROM unnest($1::VARCHAR[], $2::VARCHAR[])
+                ON CONFLICT (post_ap_id, url)
+                    DO NOTHING
+                ",
+                &[&post_ap_ids, &urls]
+            )
+        }).await??)
+    }
+
+    async fn update_new_posts(
+        &mut self,
+        objects : &HashSet<NewPost>
     ) -> Result<u64> {
         let objects = objects.clone();
-        
+
         Ok(self.client.interact(move |client| {
             let q = Self::bulk_get_query(&objects);
 
@@ -314,39 +600,47 @@ impl CrawlerDatabase {
         }).await??)
     }
 
-    async fn update_words(
+    /**
+     * Only the communities and authors of posts that weren't already stored need
+     * to be recounted, as posts never move to another community or author.
+     */
+    async fn update_post_counts(
         &mut self,
-        objects : &HashSet<Word>
+        actor_ids : &HashSet<String>
     ) -> Result<u64> {
-        let objects = objects.clone();
-        
-        Ok(self.client.interact(move |client| {
+        if actor_ids.is_empty() {
+            return Ok(0);
+        }
 
-            let params = objects.get_values();
+        let actor_ids = actor_ids.iter()
+            .cloned()
+            .collect::<Vec<_>>();
 
-            let mut values = Vec::<String>::new();
-            let mut index = 1;
-            for item in &objects {
-                let t = item.get_values().into_iter().enumerate().map(|(i, _)| {
-                    format!("${}", index + i)
-                }).collect::<Vec<_>>();
-                values.push(format!("({})", t.join(", ")));
-                index += t.len();
-            }
+        Ok(self.client.interact(move |client| {
+            update_post_counts(client, Some(&actor_ids))
+        }).await??)
+    }
 
-            let query = format!("
-                INSERT INTO {} ({})
-                    VALUES 
-                        {}
-                ON CONFLICT (word) 
-                    DO NOTHING
-            ", 
-                Word::get_table_name(),
-                Word::get_column_names().join(", "),
-                values.join(",\n\t\t\t\t")
-            );
+    async fn update_words(
+        &mut self,
+        objects : &HashSet<Word>
+    ) -> Result<u64> {
+        let ids = objects.iter().map(|word| {
+            word.id
+        }).collect::<Vec<_>>();
+        let words = objects.iter().map(|word| {
+            word.word.clone()
+        }).collect::<Vec<_>>();
 
-            client.execute(&query, &params)
+        Ok(self.client.interact(move |client| {
+            client.execute("
+                INSERT INTO words (id, word)
+                    SELECT * FROM unnest($1::UUID[], $2::VARCHAR[])
+                ON CONFLICT (word)
+                    DO NOTHING
+                ",
+                &[&ids, &words]
+            )
         }).await??)
     }
 
@@ -0,0 +1,78 @@
+use crate::{
+    error::Result,
+    database::DatabasePool,
+    api::{
+        lemmy::models::language::Language, 
+        search::models::search::SearchLanguage
+    }
+};
+
+use super::get_database_client;
+
+#[derive(Clone)]
+pub struct LanguageDBO {
+    pool : DatabasePool
+}
+
+impl LanguageDBO {
+    pub fn new(pool : DatabasePool) -> Self {
+        Self {
+            pool
+        }
+    }
+
+    pub async fn upsert_all(
+        &self,
+        languages : Vec<Language>
+    ) -> Result<u64> {
+
+        let codes = languages.iter().map(|language| {
+            language.code.clone()
+        }).collect::<Vec<_>>();
+        let names = languages.into_iter().map(|language| {
+            language.name
+        }).collect::<Vec<_>>();
+
+        get_database_client(&self.pool, move |client| {
+
+            client.execute("
+                INSERT INTO languages (\"code\", \"name\")
+                    SELECT * FROM unnest($1::VARCHAR[], $2::VARCHAR[])
+                ON CONFLICT (code)
+                DO UPDATE SET \"name\" = excluded.name
+                ",
+                &[&codes, &names]
+            )
+        }).await
+    }
+
+    /**
+     * Returns only the languages that at least one post has been written in,
+     * whether the post's author said so or it was detected.
+     */
+    pub async fn retrieve_all(
+        &self
+    ) -> Result<Vec<SearchLanguage>> {
+
+        get_database_client(&self.pool, move |client| {
+
+            client.query("
+                SELECT l.code, l.name
+                    FROM languages AS l
+                    WHERE l.code IN (
+                        SELECT DISTINCT COALESCE(language, detected_language) FROM posts
+                    )
+                    ORDER BY l.name
+                ",
+                &[]
+            ).map(|rows| {
+                rows.iter().map(|row| {
+                    SearchLanguage {
+                        code : row.get(0),
+                        name : row.get(1)
+                    }
+                }).collect()
+            })
+        }).await
+    }
+}
@@ -0,0 +1,287 @@
+use super::{
+    get_database_client,
+    update_post_counts
+};
+use crate::{
+    error::Result,
+    database::DatabasePool,
+    api::lemmy::models::{
+        author::Author, 
+        community::Community, 
+        post::{
+            Counts, 
+            Post, 
+            PostData
+        }
+    }
+};
+
+#[derive(Clone)]
+pub struct MaintenanceDatabase {
+    pool : DatabasePool
+}
+
+impl MaintenanceDatabase {
+
+    pub fn new(pool : DatabasePool) -> Self {
+        Self {
+            pool
+        }
+    }
+
+    /**
+     * Returns the number of rows in each of the tables that make up the index.
+     */
+    pub async fn get_table_counts(
+        &self
+    ) -> Result<Vec<(String, i64)>> {
+
+        get_database_client(&self.pool, move |client| {
+
+            let tables = ["sites", "authors", "communities", "community_stats", "posts", "post_counts", "lemmy_ids", "words", "xref", "links"];
+
+            tables.iter().map(|table| {
+                client.query_one(&format!("SELECT COUNT(*) FROM {}", table), &[])
+                    .map(|row| {
+                        (table.to_string(), row.get(0))
+                    })
+            }).collect()
+        }).await
+    }
+
+    /**
+     * Retrieves the next `limit` posts, ordered by their actor id, that come after
+     * the post `after`.  Used to walk the entire posts table in batches.
+     */
+    pub async fn get_posts(
+        &self,
+        after : &str,
+        limit : i64
+    ) -> Result<Vec<PostData>> {
+
+        let after = after.to_owned();
+
+        get_database_client(&self.pool, move |client| {
+
+            client.query("
+                SELECT 
+                        p.ap_id,
+                        p.url,
+                        p.name,
+                        p.body,
+                        p.score,
+                        p.language,
+                        p.detected_language,
+                        p.language_confidence,
+                        p.published,
+                        p.updated,
+                        p.comments,
+
+                        a.ap_id,
+                        a.avatar,
+                        a.name,
+                        a.display_name,
+
+                        c.ap_id,
+                        c.icon,
+                        c.name,
+                        c.title,
+
+                        COALESCE(l.post_remote_id, 0),
+
+                        c.description,
+                        c.nsfw,
+
+                        a.bio
+                    FROM posts AS p
+                        INNER JOIN authors AS a ON a.ap_id = p.author_actor_id
+                        INNER JOIN communities AS c ON c.ap_id = p.community_ap_id
+                        LEFT JOIN lemmy_ids AS l ON l.post_actor_id = p.ap_id
+                            AND p.ap_id LIKE l.instance_actor_id || '%'
+                    WHERE p.ap_id > $1
+                    ORDER BY p.ap_id
+                    LIMIT $2
+                ",
+                &[&after, &limit]
+            ).map(|rows| {
+                rows.iter().map(|row| {
+                    PostData {
+                        post : Post {
+                            id : row.get(19),
+                            ap_id : row.get(0),
+                            url : row.get(1),
+                            name : row.get(2),
+                            body : row.get(3),
+                            language : row.get(5),
+                            detected_language : row.get(6),
+                            language_confidence : row.get(7),
+                            published : row.get(8),
+                            updated : row.get(9),
+                            ..Default::default()
+                        },
+                        creator : Author {
+                            id : None,
+                            actor_id : row.get(11),
+                            avatar : row.get(12),
+                            name : row.get(13),
+                            display_name : row.get(14),
+                            bio : row.get(22)
+                        },
+                        community : Community {
+                            id : None,
+                            actor_id : row.get(15),
+                            icon : row.get(16),
+                            name : row.get(17),
+                            title : row.get(18),
+                            description : row.get(20),
+                            nsfw : row.get(21)
+                        },
+                        counts : Counts {
+                            score : row.get(4),
+                            comments : row.get::<_, Option<i64>>(10).unwrap_or(0)
+                        }
+                    }
+                }).collect()
+            })
+        }).await
+    }
+
+    /**
+     * Saves the fields that are worked out by the crawler, rather than coming from
+     * Lemmy, for each of the given posts.
+     */
+    pub async fn update_derived_fields(
+        &self,
+        posts : &[PostData]
+    ) -> Result<u64> {
+
+        let ap_ids = posts.iter().map(|post| {
+            post.post.ap_id.clone()
+        }).collect::<Vec<_>>();
+        let languages = posts.iter().map(|post| {
+            post.post.detected_language.clone()
+        }).collect::<Vec<_>>();
+        let confidences = posts.iter().map(|post| {
+            post.post.language_confidence
+        }).collect::<Vec<_>>();
+        let domains = posts.iter().map(|post| {
+            post.post.domain.clone()
+        }).collect::<Vec<_>>();
+        let normalized_urls = posts.iter().map(|post| {
+            post.post.normalized_url.clone()
+        }).collect::<Vec<_>>();
+
+        get_database_client(&self.pool, move |client| {
+
+            client.execute("
+                UPDATE posts AS p
+                    SET detected_language = d.language,
+                        language_confidence = d.confidence,
+                        domain = d.domain,
+                        normalized_url = d.normalized_url
+                    FROM unnest($1::VARCHAR[], $2::VARCHAR[], $3::FLOAT4[], $4::VARCHAR[], $5::VARCHAR[]) 
+                        AS d(ap_id, language, confidence, domain, normalized_url)
+                    WHERE p.ap_id = d.ap_id
+                ",
+                &[&ap_ids, &languages, &confidences, &domains, &normalized_urls]
+            )
+        }).await
+    }
+
+    /**
+     * Removes everything that is hosted on, or was crawled from, the given instance.
+     * This includes any posts that were made on, or made to a community on that 
+     * instance, as well as any posts written by authors from that instance.
+     * 
+     * Returns the number of posts that were removed.
+     */
+    pub async fn purge_instance(
+        &self,
+        instance_actor_id : &str
+    ) -> Result<u64> {
+
+        let instance_actor_id = instance_actor_id.to_owned();
+
+        get_database_client(&self.pool, move |client| {
+
+            let mut transaction = client.transaction()?;
+
+            let posts = "
+                SELECT ap_id 
+                    FROM posts
+                    WHERE ap_id LIKE $1 || '%'
+                        OR community_ap_id LIKE $1 || '%'
+                        OR author_actor_id LIKE $1 || '%'
+            ";
+
+            // Other communities and authors lose any posts that are removed, so
+            // they need to be recounted afterwards.
+            let actor_ids = transaction.query(&format!("
+                SELECT community_ap_id FROM posts
+                    WHERE ap_id IN ({0})
+                UNION
+                SELECT author_actor_id FROM posts
+                    WHERE ap_id IN ({0})
+                ", posts),
+                &[&instance_actor_id]
+            )?.iter().map(|row| {
+                row.get::<_, String>(0)
+            }).collect::<Vec<_>>();
+
+            transaction.execute(&format!("
+                DELETE FROM xref
+                    WHERE post_ap_id IN ({})
+                ", posts),
+                &[&instance_actor_id]
+            )?;
+            transaction.execute(&format!("
+                DELETE FROM links
+                    WHERE post_ap_id IN ({})
+                ", posts),
+                &[&instance_actor_id]
+            )?;
+            transaction.execute(&format!("
+                DELETE FROM lemmy_ids
+                    WHERE instance_actor_id = $1
+                        OR post_actor_id IN ({})
+                ", posts),
+                &[&instance_actor_id]
+            )?;
+            let count = transaction.execute(&format!("
+                DELETE FROM posts
+                    WHERE ap_id IN ({})
+                ", posts),
+                &[&instance_actor_id]
+            )?;
+            update_post_counts(&mut transaction, Some(&actor_ids))?;
+            transaction.execute("
+                DELETE FROM community_stats
+                    WHERE community_ap_id LIKE $1 || '%'
+                ",
+                &[&instance_actor_id]
+            )?;
+            transaction.execute("
+                DELETE FROM communities
+                    WHERE ap_id LIKE $1 || '%'
+                ",
+                &[&instance_actor_id]
+            )?;
+            transaction.execute("
+                DELETE FROM authors
+                    WHERE ap_id LIKE $1 || '%'
+                ",
+                &[&instance_actor_id]
+            )?;
+            transaction.execute("
+                DELETE FROM sites
+                    WHERE actor_id = $1
+                ",
+                &[&instance_actor_id]
+            )?;
+
+            transaction.commit()?;
+
+            Ok(count)
+        }).await
+    }
+}
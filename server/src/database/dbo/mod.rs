@@ -1,13 +1,19 @@
 pub mod site;
 pub mod search;
 pub mod crawler;
+pub mod language;
+pub mod maintenance;
+pub mod saved_search;
 
 use super::DatabasePool;
 use crate::error::{
     Result, 
     LemmySearchError
 };
-use postgres::Client;
+use postgres::{
+    Client,
+    GenericClient
+};
 
 pub async fn get_database_client<T, F>(
     pool : &DatabasePool,
@@ -27,3 +33,45 @@ where
         })
     }).await?
 }
+
+/**
+ * Recounts the posts that have been indexed for each of the given communities and
+ * authors, or for all of them if none are given.  Any that no longer have posts
+ * are removed, as their posts are only ever counted when they have some.
+ */
+pub fn update_post_counts<C : GenericClient>(
+    client : &mut C,
+    actor_ids : Option<&Vec<String>>
+) -> std::result::Result<u64, postgres::Error> {
+    let mut transaction = client.transaction()?;
+
+    transaction.execute("
+        DELETE FROM post_counts
+            WHERE $1::VARCHAR[] IS NULL
+                OR actor_id = any($1)
+        ",
+        &[&actor_ids]
+    )?;
+    let count = transaction.execute("
+        INSERT INTO post_counts (actor_id, posts)
+            SELECT a.actor_id, COUNT(*)
+                FROM (
+                    SELECT community_ap_id AS actor_id
+                        FROM posts
+                        WHERE $1::VARCHAR[] IS NULL
+                            OR community_ap_id = any($1)
+                    UNION ALL
+                    SELECT author_actor_id
+                        FROM posts
+                        WHERE $1::VARCHAR[] IS NULL
+                            OR author_actor_id = any($1)
+                ) AS a
+                GROUP BY a.actor_id
+        ",
+        &[&actor_ids]
+    )?;
+
+    transaction.commit()?;
+
+    Ok(count)
+}
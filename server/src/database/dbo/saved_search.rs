@@ -0,0 +1,256 @@
+use std::collections::HashSet;
+use chrono::{
+    DateTime,
+    Utc
+};
+use postgres::Row;
+use uuid::Uuid;
+use crate::{
+    error::Result,
+    database::{
+        DatabasePool,
+        schema::{
+            new_post::NewPost,
+            saved_search::SavedSearch
+        }
+    }
+};
+
+use super::get_database_client;
+
+#[derive(Clone)]
+pub struct SavedSearchDBO {
+    pool : DatabasePool
+}
+
+impl SavedSearchDBO {
+    pub fn new(pool : DatabasePool) -> Self {
+        Self {
+            pool
+        }
+    }
+
+    pub async fn create(
+        &self,
+        saved_search : &SavedSearch
+    ) -> Result<bool> {
+
+        let saved_search = saved_search.clone();
+
+        get_database_client(&self.pool, move |client| {
+
+            client.execute("
+                INSERT INTO saved_searches (\"id\", \"query\", \"preferred_instance\", \"webhook_url\", \"format\", \"created\", \"last_notified\")
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ",
+                &[
+                    &saved_search.id,
+                    &saved_search.query,
+                    &saved_search.preferred_instance,
+                    &saved_search.webhook_url,
+                    &saved_search.format,
+                    &saved_search.created,
+                    &saved_search.last_notified
+                ]
+            ).map(|count| {
+                count == 1
+            })
+        }).await
+    }
+
+    pub async fn retrieve(
+        &self,
+        id : Uuid
+    ) -> Result<Option<SavedSearch>> {
+
+        get_database_client(&self.pool, move |client| {
+
+            client.query_opt("
+                SELECT id, query, preferred_instance, webhook_url, format, created, last_notified
+                    FROM saved_searches
+                    WHERE id = $1
+                ",
+                &[&id]
+            ).map(|row| {
+                row.as_ref().map(Self::get_saved_search)
+            })
+        }).await
+    }
+
+    pub async fn retrieve_all(
+        &self
+    ) -> Result<Vec<SavedSearch>> {
+
+        get_database_client(&self.pool, move |client| {
+
+            client.query("
+                SELECT id, query, preferred_instance, webhook_url, format, created, last_notified
+                    FROM saved_searches
+                    ORDER BY created
+                ",
+                &[]
+            ).map(|rows| {
+                rows.iter().map(Self::get_saved_search).collect()
+            })
+        }).await
+    }
+
+    pub async fn delete(
+        &self,
+        id : Uuid
+    ) -> Result<bool> {
+
+        get_database_client(&self.pool, move |client| {
+
+            client.execute("
+                DELETE FROM saved_search_posts
+                    WHERE saved_search_id = $1
+                ",
+                &[&id]
+            )?;
+
+            client.execute("
+                DELETE FROM saved_searches
+                    WHERE id = $1
+                ",
+                &[&id]
+            ).map(|count| {
+                count == 1
+            })
+        }).await
+    }
+
+    /**
+     * Every post that was indexed for the first time recently enough to still be
+     * checked against the saved searches.
+     */
+    pub async fn retrieve_new_posts(
+        &self
+    ) -> Result<Vec<NewPost>> {
+
+        get_database_client(&self.pool, move |client| {
+
+            client.query("
+                SELECT post_actor_id, indexed
+                    FROM new_posts
+                ",
+                &[]
+            ).map(|rows| {
+                rows.iter().map(|row| {
+                    NewPost {
+                        post_actor_id : row.get(0),
+                        indexed : row.get(1)
+                    }
+                }).collect()
+            })
+        }).await
+    }
+
+    /**
+     * Stops checking the posts that were indexed before `before`, along with
+     * forgetting which saved searches they were sent to.
+     */
+    pub async fn remove_new_posts(
+        &self,
+        before : DateTime<Utc>
+    ) -> Result<u64> {
+
+        get_database_client(&self.pool, move |client| {
+
+            let count = client.execute("
+                DELETE FROM new_posts
+                    WHERE indexed < $1
+                ",
+                &[&before]
+            )?;
+
+            client.execute("
+                DELETE FROM saved_search_posts AS s
+                    WHERE NOT EXISTS (
+                        SELECT 1 FROM new_posts AS n WHERE n.post_actor_id = s.post_actor_id
+                    )
+                ",
+                &[]
+            )?;
+
+            Ok(count)
+        }).await
+    }
+
+    /**
+     * The new posts that have already been sent to the saved search.
+     */
+    pub async fn retrieve_sent_posts(
+        &self,
+        id : Uuid
+    ) -> Result<HashSet<String>> {
+
+        get_database_client(&self.pool, move |client| {
+
+            client.query("
+                SELECT post_actor_id
+                    FROM saved_search_posts
+                    WHERE saved_search_id = $1
+                ",
+                &[&id]
+            ).map(|rows| {
+                rows.iter().map(|row| {
+                    row.get(0)
+                }).collect()
+            })
+        }).await
+    }
+
+    pub async fn add_sent_posts(
+        &self,
+        id : Uuid,
+        post_actor_ids : &[String]
+    ) -> Result<u64> {
+
+        let post_actor_ids = post_actor_ids.to_vec();
+
+        get_database_client(&self.pool, move |client| {
+
+            client.execute("
+                INSERT INTO saved_search_posts (saved_search_id, post_actor_id)
+                    SELECT $1, unnest($2::VARCHAR[])
+                ON CONFLICT DO NOTHING
+                ",
+                &[&id, &post_actor_ids]
+            )
+        }).await
+    }
+
+    pub async fn set_last_notified(
+        &self,
+        id : Uuid
+    ) -> Result<bool> {
+
+        get_database_client(&self.pool, move |client| {
+
+            client.execute("
+                UPDATE saved_searches
+                    SET last_notified = $2
+                    WHERE id = $1
+                ",
+                &[&id, &Utc::now()]
+            ).map(|count| {
+                count == 1
+            })
+        }).await
+    }
+
+    fn get_saved_search(
+        row : &Row
+    ) -> SavedSearch {
+        SavedSearch {
+            id : row.get(0),
+            query : row.get(1),
+            preferred_instance : row.get(2),
+            webhook_url : row.get(3),
+            format : row.get(4),
+            created : row.get(5),
+            last_notified : row.get(6)
+        }
+    }
+}
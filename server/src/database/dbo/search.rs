@@ -1,19 +1,128 @@
-use std::collections::HashSet;
+use chrono::{
+    DateTime, 
+    Utc
+};
+use postgres::Row;
+use serde::{
+    Serialize, 
+    Deserialize
+};
+use url::Url;
 use super::{
     get_database_client
 };
 use crate::{
     error::Result,    
     database::DatabasePool,
+    crawler::analyzer::{
+        IndexField,
+        QueryTerm
+    },
     api::{
         search::models::search::{
             SearchPost, 
             SearchAuthor, 
-            SearchCommunity
+            SearchCommunity,
+            SearchDiscussion,
+            SearchFacet,
+            SearchFacets,
+            SearchCrossPost,
+            SearchSort,
+            SearchSuggestion,
+            SearchCommunityResult,
+            SearchAuthorResult
         }
     }
 };
 
+/**
+ * Any additional criteria, on top of the query itself, that the results must match.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    pub instance : Option<String>,
+    pub community : Option<String>,
+    pub author : Option<String>,
+    pub languages : Vec<String>,
+    /// Only posts linking to this domain, or any of its sub-domains.
+    pub site : Option<String>,
+    /// Only posts whose normalized url starts with this one.
+    pub url : Option<String>,
+    /// Only posts published at or after this time.
+    pub after : Option<DateTime<Utc>>,
+    /// Only posts published before this time.
+    pub before : Option<DateTime<Utc>>,
+    /// Only these posts, such as the ones that were just indexed.
+    pub posts : Option<Vec<String>>
+}
+
+/**
+ * How the results of a search should be ordered, grouped and paged.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub sort : SearchSort,
+    /// Only used when there's no cursor, for links to numbered pages.
+    pub page : i32,
+    pub page_size : i32,
+    /// Where the previous page left off.
+    pub cursor : Option<SearchCursor>,
+    /// How many of the most common values to count for each facet, if any.
+    pub facet_limit : Option<i64>,
+    /// Whether posts of the same link, or with the same title and body, are
+    /// returned as a single result.
+    pub collapse : bool
+}
+
+/**
+ * The position of the last result on a page, so that the next page can carry on
+ * from it even if posts were added or removed in the meantime.  The total from the
+ * first page is kept as well, so that it doesn't change from page to page.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchCursor {
+    pub sort : SearchSort,
+    pub total_results : i32,
+    pub sort_key : i64,
+    pub matches : i64,
+    pub relevance : i64,
+    pub score : i64,
+    pub ap_id : String
+}
+
+/**
+ * A word from the index that is spelled similarly to one from the query.
+ */
+#[derive(Debug, Clone)]
+pub struct SimilarWord {
+    /// The form of the query's word that this is similar to.
+    pub variant : String,
+    pub word : String,
+    /// How many posts the word appears in.
+    pub posts : i64
+}
+
+impl SearchFilters {
+
+    /**
+     * Whether any of the filters narrow down which posts match, enough that they
+     * can be searched without any words.
+     */
+    pub fn has_post_filters(
+        &self
+    ) -> bool {
+        self.instance.is_some()
+            || self.community.is_some()
+            || self.author.is_some()
+            || self.site.is_some()
+            || self.url.is_some()
+            || self.after.is_some()
+            || self.before.is_some()
+            || !self.languages.is_empty()
+            || self.posts.is_some()
+    }
+}
+
 #[derive(Clone)]
 pub struct SearchDatabase {
     pub pool : DatabasePool
@@ -21,7 +130,17 @@ pub struct SearchDatabase {
 
 impl SearchDatabase {
 
-    const PAGE_LIMIT : i32 = 50;
+    /// Every word that a community can be found by, which is indexed so that only
+    /// the communities with a word starting with one in the query are weighed up.
+    pub const COMMUNITY_DOCUMENT : &'static str = "to_tsvector('simple', regexp_replace(
+        name || ' ' || COALESCE(title, '') || ' ' || split_part(ap_id, '/', 3) || ' ' || COALESCE(description, ''),
+        '[^[:alnum:]]+', ' ', 'g'
+    ))";
+    /// The same as `COMMUNITY_DOCUMENT`, but for authors.
+    pub const AUTHOR_DOCUMENT : &'static str = "to_tsvector('simple', regexp_replace(
+        name || ' ' || COALESCE(display_name, '') || ' ' || split_part(ap_id, '/', 3) || ' ' || COALESCE(bio, ''),
+        '[^[:alnum:]]+', ' ', 'g'
+    ))";
 
     pub fn new(pool : DatabasePool) -> Self {
         Self {
@@ -31,24 +150,60 @@ impl SearchDatabase {
 
     pub async fn search(
         &self,
-        query : &HashSet<String>,
-        instance : &Option<String>,
-        community : &Option<String>,
-        author : &Option<String>,
-        preferred_instance : &str,
-        page : i32
-    ) -> Result<(Vec<SearchPost>, i32)> {        
-
-        let query = query.to_owned();
-        let instance = instance.to_owned();
-        let community = community.to_owned();
-        let author = author.to_owned();
+        query : &[QueryTerm],
+        filters : &SearchFilters,
+        options : &SearchOptions,
+        preferred_instance : &str
+    ) -> Result<(Vec<SearchPost>, i32, Option<SearchCursor>, Option<SearchFacets>)> {        
+
+        // Flatten the query out into every word to match, along with which of the
+        // user's original terms each word came from and the fields it may match.
+        let mut words = Vec::<String>::new();
+        let mut terms = Vec::<i32>::new();
+        let mut fields = Vec::<i16>::new();
+        for (index, query_term) in query.iter().enumerate() {
+            for variant in &query_term.variants {
+                words.push(variant.clone());
+                terms.push(index as i32);
+                fields.push(query_term.fields);
+            }
+        }
+
+        let weight = IndexField::ALL.iter().map(|field| {
+            format!("CASE WHEN COALESCE(x.fields, {}) & q.fields & {} <> 0 THEN {} ELSE 0 END", 
+                IndexField::Body.get_flag(), 
+                field.get_flag(), 
+                field.get_weight()
+            )
+        }).collect::<Vec<_>>().join(",\n\t\t\t\t\t\t\t\t");
+
+        if words.is_empty() && !filters.has_post_filters() {
+            return Ok((Vec::new(), 0, None, options.facet_limit.map(|_| SearchFacets::default())));
+        }
+
+        let SearchFilters {
+            instance,
+            community,
+            author,
+            languages,
+            site,
+            url,
+            after,
+            before,
+            posts
+        } = filters.to_owned();
+        let SearchOptions {
+            sort,
+            page,
+            page_size,
+            cursor,
+            facet_limit,
+            collapse
+        } = options.to_owned();
         let preferred_instance = preferred_instance.to_owned();
 
         get_database_client(&self.pool, move |client| {
 
-            let temp = Vec::<String>::from_iter(query.into_iter());
-
             let instance_query = match instance {
                 Some(_) => "AND c.ap_id LIKE $2 || '%'",
                 None => "AND $2 = $2"
@@ -61,89 +216,1011 @@ impl SearchDatabase {
                 Some(_) => "AND p.author_actor_id = $4",
                 None => "AND $4 = $4"
             };
+            let site_query = match site {
+                Some(_) => "AND (p.domain = $10 OR p.domain LIKE '%.' || $10)",
+                None => "AND $10 = $10"
+            };
+            let url_query = match url {
+                Some(_) => "AND starts_with(p.normalized_url, $11)",
+                None => "AND $11 = $11"
+            };
+
+            // Without any words every post is a candidate, so rely on the filters
+            // to narrow them down instead.
+            let matches_query = if words.is_empty() {
+                "
+                        SELECT 0 AS matches, 0 AS relevance, p.ap_id, p.url, p.name, p.body, p.author_actor_id, p.community_ap_id, p.score, p.language, p.detected_language, p.language_confidence, p.domain, p.normalized_url, p.published, p.comments
+                            FROM posts AS p
+                            WHERE cardinality($1::VARCHAR[]) = 0
+                                AND cardinality($7::INT4[]) = 0
+                                AND cardinality($9::INT2[]) = 0
+                ".to_string()
+            } else {
+                format!("
+                        SELECT COUNT(*) AS matches, SUM(t.weight) AS relevance, p.ap_id, p.url, p.name, p.body, p.author_actor_id, p.community_ap_id, p.score, p.language, p.detected_language, p.language_confidence, p.domain, p.normalized_url, p.published, p.comments
+                            FROM (
+                                SELECT q.term, x.post_ap_id, MAX(GREATEST(
+                                    {}
+                                )) AS weight
+                                    FROM unnest($1::VARCHAR[], $7::INT4[], $9::INT2[]) AS q(word, term, fields)
+                                        INNER JOIN words AS w ON w.word = q.word
+                                        INNER JOIN xref AS x ON x.word_id = w.id
+                                    WHERE COALESCE(x.fields, {}) & q.fields <> 0
+                                    GROUP BY q.term, x.post_ap_id
+                            ) AS t
+                                INNER JOIN posts AS p ON p.ap_id = t.post_ap_id
+                            GROUP BY p.ap_id
+                ", weight, IndexField::Body.get_flag())
+            };
 
             let instance = instance.unwrap_or("".to_string());
             let community = community.unwrap_or("".to_string());
             let author = author.unwrap_or("".to_string());
+            let site = site.unwrap_or("".to_string());
+            let url = url.unwrap_or("".to_string());
 
-            // Finds all words that match the search criteria, then filter those results
-            // by any additional criteria that the user may have, such as instance, 
-            // community, or author.  Next, count the number of matches each post has
-            // and sort first by the number of matches and then if there's a conflict
-            // by the total number of upvotes that the post has.
+            // Finds all words that match the search criteria, in the fields that
+            // each term is allowed to match in, then filter those results by any
+            // additional criteria that the user may have, such as instance, 
+            // community, or author.  Each of the user's terms counts once per post,
+            // weighted by the most important field that it was found in, as several
+            // forms of the same term could have matched.
+            let from_clause = format!("
+                    FROM ({}) AS p
+                INNER JOIN authors AS a ON a.ap_id = p.author_actor_id
+                INNER JOIN communities AS c ON c.ap_id = p.community_ap_id
+                INNER JOIN lemmy_ids AS l ON l.post_actor_id = p.ap_id
+                WHERE l.instance_actor_id = $5
+                    {}
+                    {}
+                    {}
+                    {}
+                    {}
+                    AND (cardinality($8::VARCHAR[]) = 0 OR COALESCE(p.language, p.detected_language) = any($8))
+                    AND ($12::TIMESTAMPTZ IS NULL OR p.published >= $12)
+                    AND ($13::TIMESTAMPTZ IS NULL OR p.published < $13)
+                    AND ($14::VARCHAR[] IS NULL OR p.ap_id = any($14))
+            ", matches_query, instance_query, community_query, author_query, site_query, url_query);
+
+            // Posts of the same link, or failing that with the same words in their
+            // title and body, are grouped together when collapsing.
+            let group_key = if collapse {
+                "COALESCE(p.normalized_url, md5(lower(regexp_replace(p.name || ' ' || COALESCE(p.body, ''), '[^[:alnum:]]+', ' ', 'g'))))"
+            } else {
+                "p.ap_id"
+            };
+
+            // A cursor skips every post up to the last one on the previous page,
+            // before any of them are ranked.  When collapsing, a group was already
+            // shown if any of its posts come before the cursor, so the whole group
+            // is skipped rather than letting its next post take its place.
+            let cursor_query = if collapse {
+                "($15::INT8 IS NULL OR (
+                        (m.sort_key, m.matches, m.relevance, m.score, m.ap_id) < ($15::INT8, $16::INT8, $17::INT8, $18::INT8, $19::VARCHAR)
+                        AND m.group_key NOT IN (
+                            SELECT s.group_key
+                                FROM matched AS s
+                                WHERE (s.sort_key, s.matches, s.relevance, s.score, s.ap_id) >= ($15::INT8, $16::INT8, $17::INT8, $18::INT8, $19::VARCHAR)
+                        )
+                    ))"
+            } else {
+                "($15::INT8 IS NULL OR (m.sort_key, m.matches, m.relevance, m.score, m.ap_id) < ($15::INT8, $16::INT8, $17::INT8, $18::INT8, $19::VARCHAR))"
+            };
+
+            // The total is only counted on the first page, after that it comes
+            // from the cursor.
+            let total_query = match cursor {
+                Some(_) => "0::INT8",
+                None => "COUNT(*) OVER()"
+            };
+
+            // Sort first by the number of terms matched, then by that weighted 
+            // relevance and then if there's still a conflict by the total number of
+            // upvotes that the post has, unless the user asked for another order.
+            // Only the first post of each group is returned, along with where the
+            // rest of the group was posted.
             let query_string = format!("
+            WITH matched AS (
+                SELECT
+                        p.url,
+                        p.name,
+                        p.body,
+                        p.language,
+                        p.detected_language,
+                        p.language_confidence,
+                        
+                        l.post_remote_id,
+                        
+                        a.ap_id AS author_ap_id,
+                        a.avatar,
+                        a.name AS author_name,
+                        a.display_name,
+                        
+                        c.ap_id AS community_ap_id,
+                        c.icon,
+                        c.name AS community_name,
+                        c.title,
+
+                        p.published,
+                        p.comments,
+
+                        p.ap_id,
+                        {sort_key} AS sort_key,
+                        p.matches::INT8 AS matches,
+                        p.relevance::INT8 AS relevance,
+                        p.score::INT8 AS score,
+
+                        l.community_remote_id,
+                        l.creator_remote_id,
+
+                        {group_key} AS group_key
+                    {from_clause}
+            ), ranked AS (
+                SELECT
+                        m.*,
+                        ROW_NUMBER() OVER (PARTITION BY m.group_key ORDER BY m.sort_key DESC, m.matches DESC, m.relevance DESC, m.score DESC, m.ap_id DESC) AS group_rank,
+                        array_agg(m.post_remote_id) OVER g AS group_remote_ids,
+                        array_agg(m.community_ap_id) OVER g AS group_community_ap_ids,
+                        array_agg(m.icon) OVER g AS group_community_icons,
+                        array_agg(m.community_name) OVER g AS group_community_names,
+                        array_agg(m.title) OVER g AS group_community_titles
+                    FROM matched AS m
+                    WHERE {cursor_query}
+                    WINDOW g AS (PARTITION BY m.group_key)
+            )
             SELECT
                     p.url,
                     p.name,
                     p.body,
+                    p.language,
+                    p.detected_language,
+                    p.language_confidence,
                     
-                    l.post_remote_id,
+                    p.post_remote_id,
                     
-                    a.ap_id,
-                    a.avatar,
-                    a.name,
-                    a.display_name,
+                    p.author_ap_id,
+                    p.avatar,
+                    p.author_name,
+                    p.display_name,
                     
-                    c.ap_id,
-                    c.icon,
-                    c.name,
-                    c.title,
-
-                    COUNT(*) OVER() AS total_results
-                    FROM (
-                        SELECT COUNT(p.ap_id) AS matches, p.ap_id, p.url, p.name, p.body, p.author_actor_id, p.community_ap_id, p.score
-                            FROM xref AS x
-                                INNER JOIN words AS w ON w.id = x.word_id
-                                INNER JOIN posts AS p ON p.ap_id = x.post_ap_id
-                            WHERE w.word = any($1)
-                            GROUP BY p.ap_id
-                    ) AS p
-                INNER JOIN authors AS a ON a.ap_id = p.author_actor_id
-                INNER JOIN communities AS c ON c.ap_id = p.community_ap_id
-                INNER JOIN lemmy_ids AS l ON l.post_actor_id = p.ap_id
-                WHERE l.instance_actor_id = $5
-                    {}
-                    {}
-                    {}
+                    p.community_ap_id,
+                    p.icon,
+                    p.community_name,
+                    p.title,
+
+                    p.published,
+                    p.comments,
+
+                    {total_query} AS total_results,
+
+                    p.group_remote_ids,
+                    p.group_community_ap_ids,
+                    p.group_community_icons,
+                    p.group_community_names,
+                    p.group_community_titles,
+
+                    p.sort_key,
+                    p.matches,
+                    p.relevance,
+                    p.score,
+                    p.ap_id,
+
+                    p.community_remote_id,
+                    p.creator_remote_id
+                FROM ranked AS p
+                WHERE p.group_rank = 1
                 ORDER BY
-                    matches DESC,
-                    p.score DESC
-                LIMIT {}
+                    p.sort_key DESC, p.matches DESC, p.relevance DESC, p.score DESC, p.ap_id DESC
+                LIMIT {limit}
                 OFFSET $6
-            ", instance_query, community_query, author_query, Self::PAGE_LIMIT);
-
-            let mut total_results = 0;
-
-            let offset = (Self::PAGE_LIMIT * (page - 1)) as i64;
-
-            let results = client.query(&query_string, &[&temp, &instance, &community, &author, &preferred_instance, &offset])
-                .map(|rows| {
-                    rows.iter().map(|row| {
-                        let temp : i64 = row.get(12);
-                        total_results = temp as i32;
-
-                        SearchPost {
-                            url : row.get(0),
-                            name : row.get(1),
-                            body : row.get(2),
-                            remote_id : row.get(3),
-                            author : SearchAuthor {
-                                actor_id: row.get(4),
-                                avatar : row.get(5),
-                                name : row.get(6),
-                                display_name : row.get(7),
-                            },
-                            community : SearchCommunity {
-                                actor_id : row.get(8),
-                                icon : row.get(9),
-                                name : row.get(10),
-                                title : row.get(11)
-                            }
+            ", 
+                group_key = group_key, 
+                from_clause = from_clause, 
+                cursor_query = cursor_query,
+                total_query = total_query,
+                sort_key = Self::get_sort_key(sort), 
+                limit = page_size + 1
+            );
+
+            // A cursor already knows where to start from.
+            let offset = match cursor {
+                Some(_) => 0,
+                None => (page_size * (page.max(1) - 1)) as i64
+            };
+            let after_sort_key = cursor.as_ref().map(|cursor| cursor.sort_key);
+            let after_matches = cursor.as_ref().map(|cursor| cursor.matches);
+            let after_relevance = cursor.as_ref().map(|cursor| cursor.relevance);
+            let after_score = cursor.as_ref().map(|cursor| cursor.score);
+            let after_ap_id = cursor.as_ref().map(|cursor| cursor.ap_id.clone());
+
+            let mut rows = client.query(&query_string, &[&words, &instance, &community, &author, &preferred_instance, &offset, &terms, &languages, &fields, &site, &url, &after, &before, &posts, &after_sort_key, &after_matches, &after_relevance, &after_score, &after_ap_id])?;
+
+            // The total only counts every result when starting from the beginning,
+            // after that the first page's total is used.
+            let total_results = match &cursor {
+                Some(cursor) => cursor.total_results,
+                None => rows.first().map(|row| {
+                    row.get::<_, i64>(17) as i32
+                }).unwrap_or(0)
+            };
+
+            // One more result than was asked for is fetched, to know if there's
+            // another page after this one.
+            let has_next = rows.len() > page_size as usize;
+            rows.truncate(page_size as usize);
+
+            let next = rows.last().filter(|_| has_next).map(|row| {
+                SearchCursor {
+                    sort,
+                    total_results,
+                    sort_key : row.get(23),
+                    matches : row.get(24),
+                    relevance : row.get(25),
+                    score : row.get(26),
+                    ap_id : row.get(27)
+                }
+            });
+
+            let results = rows.iter().map(|row| {
+                let mut post = Self::get_search_post(row);
+                post.also_posted_in = Self::get_cross_posts(row, &post);
+                post.score = row.get(26);
+                post.ap_id = row.get(27);
+                post.community_remote_id = row.get(28);
+                post.author_remote_id = row.get(29);
+                post
+            }).collect();
+
+            // The facets are counted over every matching post, not just this page,
+            // so they reuse the same parameters with the limit in place of the offset.
+            let facets = match facet_limit {
+                Some(facet_limit) => {
+                    let facet_query = format!("
+                        WITH matched AS (
+                            SELECT 
+                                    c.ap_id AS community_ap_id, 
+                                    c.name AS community_name, 
+                                    COALESCE(c.title, c.name) AS community_title,
+                                    a.ap_id AS author_ap_id,
+                                    a.name AS author_name,
+                                    COALESCE(a.display_name, a.name) AS author_display_name,
+                                    COALESCE(p.language, p.detected_language) AS language
+                            {}
+                        )
+                        (
+                            SELECT 'community', community_ap_id, MAX(community_name), MAX(community_title), COUNT(*)
+                                FROM matched
+                                GROUP BY community_ap_id
+                                ORDER BY COUNT(*) DESC
+                                LIMIT $6
+                        ) UNION ALL (
+                            SELECT 'instance', substring(community_ap_id FROM '^https?://[^/]+/'), NULL, NULL, COUNT(*)
+                                FROM matched
+                                GROUP BY 2
+                                ORDER BY COUNT(*) DESC
+                                LIMIT $6
+                        ) UNION ALL (
+                            SELECT 'author', author_ap_id, MAX(author_name), MAX(author_display_name), COUNT(*)
+                                FROM matched
+                                GROUP BY author_ap_id
+                                ORDER BY COUNT(*) DESC
+                                LIMIT $6
+                        ) UNION ALL (
+                            SELECT 'language', m.language, m.language, MAX(l.name), COUNT(*)
+                                FROM matched AS m
+                                    LEFT JOIN languages AS l ON l.code = m.language
+                                WHERE m.language IS NOT NULL
+                                GROUP BY m.language
+                                ORDER BY COUNT(*) DESC
+                                LIMIT $6
+                        )
+                    ", from_clause);
+
+                    let rows = client.query(&facet_query, &[&words, &instance, &community, &author, &preferred_instance, &facet_limit, &terms, &languages, &fields, &site, &url, &after, &before, &posts])?;
+
+                    let mut facets = SearchFacets::default();
+                    for row in rows {
+                        let kind : &str = row.get(0);
+                        let key : Option<String> = row.get(1);
+                        let name : Option<String> = row.get(2);
+                        let title : Option<String> = row.get(3);
+                        let count : i64 = row.get(4);
+
+                        let key = match key {
+                            Some(key) => key,
+                            None => continue
+                        };
+                        let host = Url::parse(&key).ok().and_then(|url| {
+                            url.host_str().map(|host| host.to_string())
+                        });
+
+                        match (kind, host) {
+                            ("community", Some(host)) => facets.communities.push(SearchFacet {
+                                name : title.unwrap_or_default(),
+                                filter : format!("community:!{}@{}", name.unwrap_or_default(), host),
+                                count
+                            }),
+                            ("instance", Some(host)) => facets.instances.push(SearchFacet {
+                                name : host.clone(),
+                                filter : format!("instance:{}", host),
+                                count
+                            }),
+                            ("author", Some(host)) => facets.authors.push(SearchFacet {
+                                name : title.unwrap_or_default(),
+                                filter : format!("author:@{}@{}", name.unwrap_or_default(), host),
+                                count
+                            }),
+                            ("language", _) => facets.languages.push(SearchFacet {
+                                name : title.unwrap_or(key.clone()),
+                                filter : format!("lang:{}", key),
+                                count
+                            }),
+                            _ => {}
                         }
-                    }).collect()
-                })?;
+                    }
+                    Some(facets)
+                },
+                None => None
+            };
+
+            Ok((results, total_results, next, facets))
+        }).await
+    }
+
+    /**
+     * Finds every post that links to the same page as `normalized_url`, grouped
+     * by the community that it was posted to.  Communities with the most posts
+     * about the page come first.
+     */
+    pub async fn get_discussions(
+        &self,
+        normalized_url : &str,
+        preferred_instance : &str
+    ) -> Result<Vec<SearchDiscussion>> {
+
+        let normalized_url = normalized_url.to_owned();
+        let preferred_instance = preferred_instance.to_owned();
+
+        get_database_client(&self.pool, move |client| {
+
+            let rows = client.query("
+                SELECT
+                        p.url,
+                        p.name,
+                        p.body,
+                        p.language,
+                        p.detected_language,
+                        p.language_confidence,
+
+                        l.post_remote_id,
+
+                        a.ap_id,
+                        a.avatar,
+                        a.name,
+                        a.display_name,
+
+                        c.ap_id,
+                        c.icon,
+                        c.name,
+                        c.title,
+
+                        p.published,
+                        p.comments
+                    FROM posts AS p
+                        INNER JOIN authors AS a ON a.ap_id = p.author_actor_id
+                        INNER JOIN communities AS c ON c.ap_id = p.community_ap_id
+                        INNER JOIN lemmy_ids AS l ON l.post_actor_id = p.ap_id
+                    WHERE p.normalized_url = $1
+                        AND l.instance_actor_id = $2
+                    ORDER BY p.score DESC
+                ",
+                &[&normalized_url, &preferred_instance]
+            )?;
+
+            let mut discussions = Vec::<SearchDiscussion>::new();
+            for row in rows {
+                let post = Self::get_search_post(&row);
+                match discussions.iter_mut().find(|discussion| {
+                    discussion.community.actor_id == post.community.actor_id
+                }) {
+                    Some(discussion) => discussion.posts.push(post),
+                    None => discussions.push(SearchDiscussion {
+                        community : post.community.clone(),
+                        posts : vec![post]
+                    })
+                }
+            }
+            discussions.sort_by(|a, b| {
+                b.posts.len().cmp(&a.posts.len())
+            });
+
+            Ok(discussions)
+        }).await
+    }
+
+    /**
+     * Finds the `name@instance` handle of the community with this id on the
+     * instance, from any of its posts that were crawled there.
+     */
+    pub async fn get_community_handle(
+        &self,
+        community_remote_id : i64,
+        instance_actor_id : &str
+    ) -> Result<Option<String>> {
+
+        let instance_actor_id = instance_actor_id.to_owned();
+
+        get_database_client(&self.pool, move |client| {
+            let row = client.query_opt("
+                SELECT c.name, c.ap_id
+                    FROM lemmy_ids AS l
+                        INNER JOIN posts AS p ON p.ap_id = l.post_actor_id
+                        INNER JOIN communities AS c ON c.ap_id = p.community_ap_id
+                    WHERE l.community_remote_id = $1
+                        AND l.instance_actor_id = $2
+                    LIMIT 1
+                ",
+                &[&community_remote_id, &instance_actor_id]
+            )?;
+
+            Ok(row.and_then(|row| {
+                Self::get_handle(row.get(0), row.get(1))
+            }))
+        }).await
+    }
+
+    /**
+     * Finds the `name@instance` handle of the author with this id on the instance,
+     * from any of their posts that were crawled there.
+     */
+    pub async fn get_author_handle(
+        &self,
+        author_remote_id : i64,
+        instance_actor_id : &str
+    ) -> Result<Option<String>> {
+
+        let instance_actor_id = instance_actor_id.to_owned();
+
+        get_database_client(&self.pool, move |client| {
+            let row = client.query_opt("
+                SELECT a.name, a.ap_id
+                    FROM lemmy_ids AS l
+                        INNER JOIN posts AS p ON p.ap_id = l.post_actor_id
+                        INNER JOIN authors AS a ON a.ap_id = p.author_actor_id
+                    WHERE l.creator_remote_id = $1
+                        AND l.instance_actor_id = $2
+                    LIMIT 1
+                ",
+                &[&author_remote_id, &instance_actor_id]
+            )?;
+
+            Ok(row.and_then(|row| {
+                Self::get_handle(row.get(0), row.get(1))
+            }))
+        }).await
+    }
+
+    fn get_handle(
+        name : String,
+        actor_id : String
+    ) -> Option<String> {
+        let host = Url::parse(&actor_id).ok()?
+            .host_str()?
+            .to_string();
+        Some(format!("{}@{}", name, host))
+    }
+
+    /**
+     * Finds the communities whose name, title, instance or description contain the
+     * words of the query, with matches in the name counting the most.  Ties are
+     * broken by how many subscribers and active users the community has.  Without
+     * any words, every community is returned.
+     */
+    pub async fn search_communities(
+        &self,
+        query : &[QueryTerm],
+        instance : Option<String>,
+        include_nsfw : bool,
+        page : i32,
+        page_size : i32
+    ) -> Result<(Vec<SearchCommunityResult>, i32)> {
+
+        let mut words = Vec::<String>::new();
+        let mut terms = Vec::<i32>::new();
+        for (index, query_term) in query.iter().enumerate() {
+            for variant in &query_term.variants {
+                words.push(variant.clone());
+                terms.push(index as i32);
+            }
+        }
+
+        let prefix_query = Self::get_prefix_query(&words);
+
+        get_database_client(&self.pool, move |client| {
+
+            let instance_query = match instance {
+                Some(_) => "AND c.ap_id LIKE $3 || '%'",
+                None => "AND $3 = $3"
+            };
+            let document_query = match prefix_query {
+                Some(_) => format!("AND {} @@ to_tsquery('simple', $6)", Self::COMMUNITY_DOCUMENT),
+                None => "AND $6::VARCHAR IS NULL".to_string()
+            };
+
+            let instance = instance.unwrap_or("".to_string());
+            let offset = (page_size * (page.max(1) - 1)) as i64;
+
+            // Words only ever contain letters and numbers, so they're safe to use
+            // in a regular expression as-is.
+            let query_string = format!("
+                SELECT
+                        c.ap_id,
+                        c.icon,
+                        c.name,
+                        c.title,
+                        c.description,
+                        c.nsfw,
+                        s.subscribers,
+                        s.users_active_month,
+                        COALESCE(n.posts, 0) AS posts,
+                        COUNT(*) OVER() AS total_results
+                    FROM communities AS c
+                        INNER JOIN LATERAL (
+                            SELECT COALESCE(SUM(t.weight), 0) AS relevance
+                                FROM (
+                                    SELECT q.term, MAX(CASE
+                                        WHEN replace(c.name, '_', ' ') ~* ('\\m' || q.word) THEN 10
+                                        WHEN c.title ~* ('\\m' || q.word) THEN 6
+                                        WHEN split_part(c.ap_id, '/', 3) ~* ('\\m' || q.word) THEN 4
+                                        WHEN c.description ~* ('\\m' || q.word) THEN 2
+                                        ELSE 0
+                                    END) AS weight
+                                        FROM unnest($1::VARCHAR[], $2::INT4[]) AS q(word, term)
+                                        GROUP BY q.term
+                                ) AS t
+                        ) AS m ON m.relevance > 0 OR cardinality($1::VARCHAR[]) = 0
+                        LEFT JOIN community_stats AS s ON s.community_ap_id = c.ap_id
+                        LEFT JOIN post_counts AS n ON n.actor_id = c.ap_id
+                    WHERE ($4 OR NOT COALESCE(c.nsfw, false))
+                        {}
+                        {}
+                    ORDER BY m.relevance DESC, s.subscribers DESC NULLS LAST, s.users_active_month DESC NULLS LAST, posts DESC
+                    LIMIT {}
+                    OFFSET $5
+                ", instance_query, document_query, page_size);
+
+            let rows = client.query(&query_string,
+                &[&words, &terms, &instance, &include_nsfw, &offset, &prefix_query]
+            )?;
+
+            let total_results = rows.first().map(|row| {
+                row.get::<_, i64>(9) as i32
+            }).unwrap_or(0);
+
+            let communities = rows.iter().map(|row| {
+                SearchCommunityResult {
+                    community : SearchCommunity {
+                        actor_id : row.get(0),
+                        icon : row.get(1),
+                        name : row.get(2),
+                        title : row.get(3)
+                    },
+                    url : String::new(),
+                    description : row.get(4),
+                    snippet : None,
+                    highlights : Vec::new(),
+                    nsfw : row.get::<_, Option<bool>>(5).unwrap_or(false),
+                    subscribers : row.get(6),
+                    active_users_month : row.get(7),
+                    posts : row.get(8)
+                }
+            }).collect();
+
+            Ok((communities, total_results))
+        }).await
+    }
+
+    /**
+     * Matches any of the words at the start of a word in a community's or author's
+     * document, the same as the `\m` in their regular expressions.  Words only ever
+     * contain letters and numbers, so none of them can be read as an operator.
+     */
+    fn get_prefix_query(
+        words : &[String]
+    ) -> Option<String> {
+        if words.is_empty() {
+            return None;
+        }
+
+        Some(words.iter().map(|word| {
+            format!("{}:*", word)
+        }).collect::<Vec<_>>().join(" | "))
+    }
+
+    /**
+     * Finds the authors whose name, display name, instance or bio contain the words
+     * of the query, with matches in the name counting the most.  Ties are broken by
+     * how many of their posts have been indexed.  Without any words, every author
+     * is returned.
+     */
+    pub async fn search_authors(
+        &self,
+        query : &[QueryTerm],
+        instance : Option<String>,
+        page : i32,
+        page_size : i32
+    ) -> Result<(Vec<SearchAuthorResult>, i32)> {
+
+        let mut words = Vec::<String>::new();
+        let mut terms = Vec::<i32>::new();
+        for (index, query_term) in query.iter().enumerate() {
+            for variant in &query_term.variants {
+                words.push(variant.clone());
+                terms.push(index as i32);
+            }
+        }
+
+        let prefix_query = Self::get_prefix_query(&words);
+
+        get_database_client(&self.pool, move |client| {
+
+            let instance_query = match instance {
+                Some(_) => "WHERE a.ap_id LIKE $3 || '%'",
+                None => "WHERE $3 = $3"
+            };
+            let document_query = match prefix_query {
+                Some(_) => format!("AND {} @@ to_tsquery('simple', $5)", Self::AUTHOR_DOCUMENT),
+                None => "AND $5::VARCHAR IS NULL".to_string()
+            };
+
+            let instance = instance.unwrap_or("".to_string());
+            let offset = (page_size * (page.max(1) - 1)) as i64;
+
+            // Words only ever contain letters and numbers, so they're safe to use
+            // in a regular expression as-is.
+            let query_string = format!("
+                SELECT
+                        a.ap_id,
+                        a.avatar,
+                        a.name,
+                        a.display_name,
+                        a.bio,
+                        COALESCE(n.posts, 0) AS posts,
+                        COUNT(*) OVER() AS total_results
+                    FROM authors AS a
+                        INNER JOIN LATERAL (
+                            SELECT COALESCE(SUM(t.weight), 0) AS relevance
+                                FROM (
+                                    SELECT q.term, MAX(CASE
+                                        WHEN replace(a.name, '_', ' ') ~* ('\\m' || q.word) THEN 10
+                                        WHEN a.display_name ~* ('\\m' || q.word) THEN 6
+                                        WHEN split_part(a.ap_id, '/', 3) ~* ('\\m' || q.word) THEN 4
+                                        WHEN a.bio ~* ('\\m' || q.word) THEN 2
+                                        ELSE 0
+                                    END) AS weight
+                                        FROM unnest($1::VARCHAR[], $2::INT4[]) AS q(word, term)
+                                        GROUP BY q.term
+                                ) AS t
+                        ) AS m ON m.relevance > 0 OR cardinality($1::VARCHAR[]) = 0
+                        LEFT JOIN post_counts AS n ON n.actor_id = a.ap_id
+                    {}
+                        {}
+                    ORDER BY m.relevance DESC, posts DESC, a.name
+                    LIMIT {}
+                    OFFSET $4
+                ", instance_query, document_query, page_size);
+
+            let rows = client.query(&query_string,
+                &[&words, &terms, &instance, &offset, &prefix_query]
+            )?;
+
+            let total_results = rows.first().map(|row| {
+                row.get::<_, i64>(6) as i32
+            }).unwrap_or(0);
+
+            let authors = rows.iter().map(|row| {
+                SearchAuthorResult {
+                    author : SearchAuthor {
+                        actor_id : row.get(0),
+                        avatar : row.get(1),
+                        name : row.get(2),
+                        display_name : row.get(3)
+                    },
+                    url : String::new(),
+                    bio : row.get(4),
+                    snippet : None,
+                    highlights : Vec::new(),
+                    posts : row.get(5)
+                }
+            }).collect();
+
+            Ok((authors, total_results))
+        }).await
+    }
+
+    /**
+     * Finds the indexed words that share the most trigrams with each of the given
+     * words, including the word itself if it was indexed.  This requires the pg_trgm
+     * extension.
+     */
+    pub async fn get_similar_words(
+        &self,
+        words : Vec<String>,
+        limit : i64
+    ) -> Result<Vec<SimilarWord>> {
 
-            Ok((results, total_results))
+        get_database_client(&self.pool, move |client| {
+
+            let mut transaction = client.transaction()?;
+
+            // The default threshold of 0.3 misses most swapped letters in shorter
+            // words, the edit distance is checked afterwards anyways.
+            transaction.batch_execute("
+                SET LOCAL pg_trgm.similarity_threshold = 0.2
+            ")?;
+
+            let rows = transaction.query("
+                SELECT
+                        v.variant,
+                        w.word,
+                        (SELECT COUNT(*) FROM xref AS x WHERE x.word_id = w.id) AS posts
+                    FROM unnest($1::VARCHAR[]) AS v(variant)
+                        CROSS JOIN LATERAL (
+                            SELECT id, word
+                                FROM words
+                                WHERE word % v.variant
+                                ORDER BY word <-> v.variant
+                                LIMIT $2
+                        ) AS w
+                ",
+                &[&words, &limit]
+            )?;
+
+            transaction.commit()?;
+
+            Ok(rows.iter().map(|row| {
+                SimilarWord {
+                    variant : row.get(0),
+                    word : row.get(1),
+                    posts : row.get(2)
+                }
+            }).collect())
         }).await
     }
+
+    /**
+     * Finds the indexed words that start with the prefix, ordered by how many posts
+     * each of them appears in.
+     */
+    pub async fn suggest_words(
+        &self,
+        prefix : &str,
+        limit : i64
+    ) -> Result<Vec<SearchSuggestion>> {
+
+        let pattern = format!("{}%", Self::escape_like(prefix));
+
+        get_database_client(&self.pool, move |client| {
+
+            let rows = client.query("
+                SELECT
+                        w.word,
+                        COUNT(x.post_ap_id) AS posts
+                    FROM words AS w
+                        INNER JOIN xref AS x ON x.word_id = w.id
+                    WHERE w.word LIKE $1
+                    GROUP BY w.word
+                    ORDER BY posts DESC, w.word
+                    LIMIT $2
+                ",
+                &[&pattern, &limit]
+            )?;
+
+            Ok(rows.iter().map(Self::get_suggestion).collect())
+        }).await
+    }
+
+    /**
+     * Finds the communities whose handle, in the form of !name@instance, starts
+     * with the prefix, or whose title does.  The communities with the most posts
+     * come first.
+     */
+    pub async fn suggest_communities(
+        &self,
+        prefix : &str,
+        limit : i64
+    ) -> Result<Vec<SearchSuggestion>> {
+
+        let pattern = format!("{}%", Self::escape_like(prefix));
+
+        get_database_client(&self.pool, move |client| {
+
+            let rows = client.query("
+                SELECT
+                        '!' || c.name || '@' || split_part(c.ap_id, '/', 3) AS handle,
+                        COUNT(p.ap_id) AS posts
+                    FROM communities AS c
+                        LEFT JOIN posts AS p ON p.community_ap_id = c.ap_id
+                    WHERE lower('!' || c.name || '@' || split_part(c.ap_id, '/', 3)) LIKE '!' || $1
+                        OR lower(c.title) LIKE $1
+                    GROUP BY c.ap_id, c.name
+                    ORDER BY posts DESC, handle
+                    LIMIT $2
+                ",
+                &[&pattern, &limit]
+            )?;
+
+            Ok(rows.iter().map(Self::get_suggestion).collect())
+        }).await
+    }
+
+    /**
+     * Finds the authors whose handle, in the form of @name@instance, starts with
+     * the prefix, or whose display name does.  The authors with the most posts
+     * come first.
+     */
+    pub async fn suggest_authors(
+        &self,
+        prefix : &str,
+        limit : i64
+    ) -> Result<Vec<SearchSuggestion>> {
+
+        let pattern = format!("{}%", Self::escape_like(prefix));
+
+        get_database_client(&self.pool, move |client| {
+
+            let rows = client.query("
+                SELECT
+                        '@' || a.name || '@' || split_part(a.ap_id, '/', 3) AS handle,
+                        COUNT(p.ap_id) AS posts
+                    FROM authors AS a
+                        LEFT JOIN posts AS p ON p.author_actor_id = a.ap_id
+                    WHERE lower('@' || a.name || '@' || split_part(a.ap_id, '/', 3)) LIKE '@' || $1
+                        OR lower(a.display_name) LIKE $1
+                    GROUP BY a.ap_id, a.name
+                    ORDER BY posts DESC, handle
+                    LIMIT $2
+                ",
+                &[&pattern, &limit]
+            )?;
+
+            Ok(rows.iter().map(Self::get_suggestion).collect())
+        }).await
+    }
+
+    fn get_suggestion(
+        row : &Row
+    ) -> SearchSuggestion {
+        SearchSuggestion {
+            text : String::new(),
+            term : row.get(0),
+            count : row.get(1)
+        }
+    }
+
+    /**
+     * Escapes the characters that LIKE treats as wildcards, so that user input is
+     * only ever matched literally.
+     */
+    fn escape_like(
+        value : &str
+    ) -> String {
+        value.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    }
+
+    /**
+     * Reads a post from the first columns of the row, which must be selected in
+     * the same order as `search` does.
+     */
+    fn get_search_post(
+        row : &Row
+    ) -> SearchPost {
+        SearchPost {
+            url : row.get(0),
+            name : row.get(1),
+            body : row.get(2),
+            snippet : None,
+            highlights : Vec::new(),
+            language : row.get(3),
+            detected_language : row.get(4),
+            language_confidence : row.get(5),
+            remote_id : row.get(6),
+            author : SearchAuthor {
+                actor_id: row.get(7),
+                avatar : row.get(8),
+                name : row.get(9),
+                display_name : row.get(10),
+            },
+            community : SearchCommunity {
+                actor_id : row.get(11),
+                icon : row.get(12),
+                name : row.get(13),
+                title : row.get(14)
+            },
+            published : row.get(15),
+            comments : row.get(16),
+            also_posted_in : Vec::new(),
+            ap_id : String::new(),
+            score : None,
+            community_remote_id : None,
+            author_remote_id : None
+        }
+    }
+
+    /**
+     * Reads every other post in the same group as the post, from the arrays that
+     * follow the total in the row.
+     */
+    fn get_cross_posts(
+        row : &Row,
+        post : &SearchPost
+    ) -> Vec<SearchCrossPost> {
+        let remote_ids : Vec<i64> = row.get(18);
+        let actor_ids : Vec<String> = row.get(19);
+        let icons : Vec<Option<String>> = row.get(20);
+        let names : Vec<String> = row.get(21);
+        let titles : Vec<Option<String>> = row.get(22);
+
+        remote_ids.into_iter()
+            .zip(actor_ids)
+            .zip(icons)
+            .zip(names)
+            .zip(titles)
+            .filter(|((((remote_id, _), _), _), _)| {
+                *remote_id != post.remote_id
+            })
+            .map(|((((remote_id, actor_id), icon), name), title)| {
+                SearchCrossPost {
+                    remote_id,
+                    community : SearchCommunity {
+                        actor_id,
+                        icon,
+                        name,
+                        title
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /**
+     * Posts are always ordered by the sort first, then any ties fall back to
+     * how well the post matched, its score and finally its id, so that every post
+     * has a distinct position to continue on from.  Posts that are missing the
+     * value being sorted by come last.
+     */
+    fn get_sort_key(
+        sort : SearchSort
+    ) -> &'static str {
+        match sort {
+            SearchSort::Relevance => "0::INT8",
+            SearchSort::New => "COALESCE((EXTRACT(EPOCH FROM p.published) * 1000000)::INT8, -1)",
+            SearchSort::Top => "p.score::INT8",
+            SearchSort::Comments => "COALESCE(p.comments, -1)::INT8"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SearchFilters;
+
+    #[test]
+    fn searches_without_words_only_when_filtered() {
+        assert!(!SearchFilters::default().has_post_filters());
+
+        let filters = SearchFilters {
+            languages : vec!["de".to_string()],
+            ..Default::default()
+        };
+        assert!(filters.has_post_filters());
+
+        let filters = SearchFilters {
+            posts : Some(vec!["https://lemmy.ml/post/1".to_string()]),
+            ..Default::default()
+        };
+        assert!(filters.has_post_filters());
+    }
 }
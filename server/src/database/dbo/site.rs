@@ -96,6 +96,62 @@ impl SiteDBO {
         }).await
     }
 
+    /**
+     * Works out which language most of each instance's own posts are written in,
+     * so that searches from the instance can be analyzed in that language.
+     */
+    pub async fn update_languages(
+        &self
+    ) -> Result<u64> {
+
+        get_database_client(&self.pool, move |client| {
+
+            client.execute("
+                UPDATE sites AS s
+                    SET language = l.language
+                    FROM (
+                        SELECT DISTINCT ON (c.instance) c.instance, c.language
+                            FROM (
+                                SELECT 
+                                        substring(p.ap_id FROM '^https?://[^/]+/') AS instance,
+                                        COALESCE(p.language, p.detected_language) AS language,
+                                        COUNT(*) AS posts
+                                    FROM posts AS p
+                                    WHERE COALESCE(p.language, p.detected_language) IS NOT NULL
+                                    GROUP BY 1, 2
+                            ) AS c
+                            ORDER BY c.instance, c.posts DESC
+                    ) AS l
+                    WHERE s.actor_id = l.instance
+                ",
+                &[]
+            )
+        }).await
+    }
+
+    pub async fn get_language(
+        &self,
+        ap_id : &str
+    ) -> Result<Option<String>> {
+
+        let ap_id = ap_id.to_owned();
+
+        get_database_client(&self.pool, move |client| {
+
+            client.query_opt("
+                SELECT language
+                    FROM sites
+                    WHERE actor_id = $1
+                ",
+                &[&ap_id]
+            ).map(|row| {
+                row.and_then(|row| {
+                    row.get("language")
+                })
+            })
+        }).await
+    }
+
     #[allow(unused)]
     pub async fn set_last_comment_page(
         &self,
@@ -7,7 +7,13 @@ use crate::{
         schema::{
             site::Site,
             word::Word, 
-            xref::Search
+            xref::Search,
+            link::Link,
+            community_stats::CommunityStats,
+            new_post::NewPost,
+            post_count::PostCount,
+            saved_search::SavedSearch,
+            saved_search_post::SavedSearchPost
         }
     }, 
     error::{
@@ -19,9 +25,14 @@ use crate::{
         author::Author, 
         community::Community, 
         post::PostData, 
-        id::LemmyId
+        id::LemmyId, 
+        language::Language
     }
 };
+use std::time::{
+    Duration, 
+    Instant
+};
 use deadpool_r2d2::Runtime;
 use postgres::{
     NoTls, 
@@ -29,7 +40,14 @@ use postgres::{
 };
 use r2d2_postgres::PostgresConnectionManager;
 
-use self::schema::DatabaseSchema;
+use self::{
+    dbo::{
+        get_database_client,
+        update_post_counts,
+        search::SearchDatabase
+    },
+    schema::DatabaseSchema
+};
 
 pub type DatabasePool = deadpool_r2d2::Pool<PgManager>;
 
@@ -45,6 +63,54 @@ pub struct Database {
 
 impl Database {
 
+    const DEFAULT_STARTUP_TIMEOUT : u64 = 60;
+    const MAX_RETRY_DELAY : Duration = Duration::from_secs(16);
+
+    /**
+     * Creates the database pool and initializes the schema.  Should either step
+     * fail, e.g. because the database is still starting up, this will keep
+     * retrying with an exponential backoff until `startup_timeout` has elapsed.
+     */
+    pub async fn connect_with_retry(
+        config : &Postgres
+    ) -> Result<Self> {
+        let timeout = Duration::from_secs(
+            config.startup_timeout.unwrap_or(Self::DEFAULT_STARTUP_TIMEOUT)
+        );
+        let start = Instant::now();
+
+        let mut delay = Duration::from_secs(1);
+        let mut attempt = 1;
+        loop {
+            println!("Connecting to the database (attempt {})...", attempt);
+
+            let result = match Self::create(config).await {
+                Ok(database) => database.init_database()
+                    .await
+                    .map(|_| database),
+                Err(err) => Err(err)
+            };
+
+            match result {
+                Ok(database) => return Ok(database),
+                Err(err) => {
+                    println!("\t...attempt {} failed.", attempt);
+                    if config.log {
+                        println!("{}", err);
+                    }
+                    if start.elapsed() + delay > timeout {
+                        return Err(err);
+                    }
+                    println!("\t...retrying in {} seconds.", delay.as_secs());
+                    tokio::time::sleep(delay).await;
+
+                    delay = (delay * 2).min(Self::MAX_RETRY_DELAY);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
     pub async fn create(
         config : &Postgres
     ) -> std::result::Result<Self, LemmySearchError> {
@@ -67,6 +133,7 @@ impl Database {
             .host(&config.hostname)
             .port(config.port)
             .dbname(&config.database)
+            .connect_timeout(Duration::from_secs(5))
             .to_owned();
 
         let r2d2_manager = PostgresConnectionManager::new(
@@ -77,8 +144,8 @@ impl Database {
         DatabasePool::builder(manager)
             .max_size(config.max_size)
             .build()
-            .map_err(|_| {
-                LemmySearchError::Unknown("".to_string())
+            .map_err(|err| {
+                LemmySearchError::Unknown(err.to_string())
             })
     }
 
@@ -95,18 +162,95 @@ impl Database {
             .await?;
         self.create_table_from_schema::<Community>(drop_table)
             .await?;
+        self.create_table_from_schema::<CommunityStats>(drop_table)
+            .await?;
         self.create_table_from_schema::<PostData>(drop_table)
             .await?;
         self.create_table_from_schema::<LemmyId>(drop_table)
             .await?;
+        self.create_table_from_schema::<Language>(drop_table)
+            .await?;
         self.create_table_from_schema::<Word>(drop_table)
             .await?;
         self.create_table_from_schema::<Search>(drop_table)
             .await?;
+        self.create_table_from_schema::<Link>(drop_table)
+            .await?;
+        self.create_table_from_schema::<SavedSearch>(drop_table)
+            .await?;
+        self.create_table_from_schema::<SavedSearchPost>(drop_table)
+            .await?;
+        self.create_table_from_schema::<NewPost>(drop_table)
+            .await?;
+        self.create_table_from_schema::<PostCount>(drop_table)
+            .await?;
+
+        self.create_search_indexes()
+            .await?;
+
+        self.create_word_similarity_index()
+            .await;
 
         Ok(())
     }
 
+    /**
+     * Enables the pg_trgm extension and indexes every word by its trigrams, so that
+     * misspelled words in a query can be matched to similar ones.  If the extension
+     * isn't available, searches still work but misspellings won't be corrected.
+     */
+    async fn create_word_similarity_index(
+        &self
+    ) {
+        println!("\tCreating word similarity index...");
+
+        let log = self.config.log;
+
+        let client = match self.pool.get().await {
+            Ok(client) => client,
+            Err(_) => return
+        };
+
+        let _ = client.interact(move |client| -> Result<()> {
+            client.batch_execute("
+                CREATE EXTENSION IF NOT EXISTS pg_trgm;
+                CREATE INDEX IF NOT EXISTS words_word_trgm_idx ON words USING GIN (word gin_trgm_ops);
+            ").map_err(|err| {
+                LemmySearchError::Database(err)
+            }).log_error("...the word similarity index could not be created, misspelled words won't be corrected", log)
+        }).await;
+    }
+
+    /**
+     * Indexes the posts by their community and author, and the communities and
+     * authors by the words that they can be searched for.  The post counts are
+     * also worked out for any posts that were indexed before they were counted.
+     */
+    async fn create_search_indexes(
+        &self
+    ) -> Result<()> {
+        println!("\tCreating search indexes...");
+
+        let query = format!("
+            CREATE INDEX IF NOT EXISTS posts_community_ap_id_idx ON posts (community_ap_id);
+            CREATE INDEX IF NOT EXISTS posts_author_actor_id_idx ON posts (author_actor_id);
+            CREATE INDEX IF NOT EXISTS communities_document_idx ON communities USING GIN ({});
+            CREATE INDEX IF NOT EXISTS authors_document_idx ON authors USING GIN ({});
+        ", SearchDatabase::COMMUNITY_DOCUMENT, SearchDatabase::AUTHOR_DOCUMENT);
+
+        get_database_client(&self.pool, move |client| {
+            client.batch_execute(&query)?;
+
+            let counted = client.query_one("SELECT EXISTS (SELECT 1 FROM post_counts)", &[])?
+                .get::<_, bool>(0);
+            if !counted {
+                update_post_counts(client, None)?;
+            }
+
+            Ok(())
+        }).await
+    }
+
     async fn create_table_from_schema<S : DatabaseSchema>(
         &self,
         drop : bool
@@ -116,8 +260,8 @@ impl Database {
         let column_types = S::get_column_types();
         let primary_keys = S::get_keys();
 
-        let columns = column_names.into_iter().map(|name| {
-            format!("{}\t{}", name, column_types[&name].to_sql_type_name())
+        let columns = column_names.iter().map(|name| {
+            format!("{}\t{}", name, column_types[name].to_sql_type_name())
         }).collect::<Vec<_>>()
             .join(",\n");
 
@@ -140,6 +284,14 @@ impl Database {
             )
         ", table_name, columns, primary_key);
 
+        // Tables created by an older version won't have any of the newer columns.
+        // Any column added after the fact should therefore be nullable.
+        let add_columns = column_names.iter().map(|name| {
+            format!("
+                ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} {}
+            ", table_name, name, column_types[name].to_sql_type_name())
+        }).collect::<Vec<_>>();
+
         let log: bool = self.config.log;
 
         let client = self.pool.get()
@@ -150,8 +302,10 @@ impl Database {
                 client.execute(&drop_table, &[])?;
             }
 
-            client.execute(&create_table, &[]).map(|_| {
-                ()
+            client.execute(&create_table, &[]).and_then(|_| {
+                add_columns.iter().try_for_each(|add_column| {
+                    client.execute(add_column, &[]).map(|_| ())
+                })
             }).map_err(|err| {
                 LemmySearchError::Database(err)
             }).log_error(format!("...table creation failed for table '{}'", S::get_table_name()).as_str(), log)
@@ -24,7 +24,8 @@ impl DatabaseSchema for Author {
             "ap_id".to_string(),
             "avatar".to_string(),
             "name".to_string(),
-            "display_name".to_string()
+            "display_name".to_string(),
+            "bio".to_string()
         ]
     }
 
@@ -35,7 +36,8 @@ impl DatabaseSchema for Author {
             ("ap_id".to_string(), DatabaseType::String(0).not_null()),
             ("avatar".to_string(), DatabaseType::String(0).nullable()),
             ("name".to_string(), DatabaseType::String(0).not_null()),
-            ("display_name".to_string(), DatabaseType::String(0).nullable())
+            ("display_name".to_string(), DatabaseType::String(0).nullable()),
+            ("bio".to_string(), DatabaseType::String(0).nullable())
         ])
     }
 
@@ -46,7 +48,8 @@ impl DatabaseSchema for Author {
             &self.actor_id,
             &self.avatar,
             &self.name,
-            &self.display_name
+            &self.display_name,
+            &self.bio
         ]
     }
 }
@@ -24,7 +24,9 @@ impl DatabaseSchema for Community {
             "ap_id".to_string(),
             "icon".to_string(),
             "name".to_string(),
-            "title".to_string()
+            "title".to_string(),
+            "description".to_string(),
+            "nsfw".to_string()
         ]
     }
 
@@ -35,7 +37,9 @@ impl DatabaseSchema for Community {
             ("ap_id".to_string(), DatabaseType::String(0).not_null()),
             ("icon".to_string(), DatabaseType::String(0).nullable()),
             ("name".to_string(), DatabaseType::String(0).not_null()),
-            ("title".to_string(), DatabaseType::String(0).nullable())
+            ("title".to_string(), DatabaseType::String(0).nullable()),
+            ("description".to_string(), DatabaseType::String(0).nullable()),
+            ("nsfw".to_string(), DatabaseType::Bool.nullable())
         ])
     }
 
@@ -46,7 +50,9 @@ impl DatabaseSchema for Community {
             &self.actor_id,
             &self.icon,
             &self.name,
-            &self.title
+            &self.title,
+            &self.description,
+            &self.nsfw
         ]
     }
 }
@@ -0,0 +1,97 @@
+use std::{
+    hash::Hash, 
+    collections::HashMap
+};
+use postgres::types::ToSql;
+use crate::api::lemmy::models::community::CommunityView;
+use super::{
+    DatabaseSchema, 
+    DatabaseType
+};
+
+/**
+ * How popular and active a community is, as last reported by the instance that
+ * it was crawled from.  These are kept apart from the community itself, as they
+ * are only known when crawling the list of communities, not their posts.
+ */
+#[derive(Debug, Clone)]
+pub struct CommunityStats {
+    pub community_ap_id : String,
+    pub subscribers : i64,
+    pub posts : i64,
+    pub comments : i64,
+    pub users_active_month : i64
+}
+
+impl DatabaseSchema for CommunityStats {
+
+    fn get_table_name(
+
+    ) -> String {
+        "community_stats".to_string()
+    }
+
+    fn get_column_names(
+    
+    ) -> Vec<String> {
+        vec![
+            "community_ap_id".to_string(),
+            "subscribers".to_string(),
+            "posts".to_string(),
+            "comments".to_string(),
+            "users_active_month".to_string()
+        ]
+    }
+
+    fn get_column_types(
+    
+    ) -> HashMap<String, DatabaseType> {
+        HashMap::from([
+            ("community_ap_id".to_string(), DatabaseType::String(0).not_null()),
+            ("subscribers".to_string(), DatabaseType::I64.nullable()),
+            ("posts".to_string(), DatabaseType::I64.nullable()),
+            ("comments".to_string(), DatabaseType::I64.nullable()),
+            ("users_active_month".to_string(), DatabaseType::I64.nullable())
+        ])
+    }
+
+    fn get_values(
+        &self
+    ) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.community_ap_id,
+            &self.subscribers,
+            &self.posts,
+            &self.comments,
+            &self.users_active_month
+        ]
+    }
+}
+
+impl PartialEq for CommunityStats {
+    fn eq(&self, other: &Self) -> bool {
+        self.community_ap_id == other.community_ap_id
+    }
+}
+
+impl Eq for CommunityStats {
+
+}
+
+impl Hash for CommunityStats {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.community_ap_id.hash(state);
+    }
+}
+
+impl From<&CommunityView> for CommunityStats {
+    fn from(view : &CommunityView) -> Self {
+        Self {
+            community_ap_id : view.community.actor_id.clone(),
+            subscribers : view.counts.subscribers,
+            posts : view.counts.posts,
+            comments : view.counts.comments,
+            users_active_month : view.counts.users_active_month
+        }
+    }
+}
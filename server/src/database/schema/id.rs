@@ -32,7 +32,9 @@ impl DatabaseSchema for LemmyId {
         vec![
             "post_remote_id".to_string(),
             "post_actor_id".to_string(),
-            "instance_actor_id".to_string()
+            "instance_actor_id".to_string(),
+            "community_remote_id".to_string(),
+            "creator_remote_id".to_string()
         ]
     }
 
@@ -42,7 +44,9 @@ impl DatabaseSchema for LemmyId {
         HashMap::from([
             ("post_remote_id".to_string(), DatabaseType::I64.not_null()),
             ("post_actor_id".to_string(), DatabaseType::String(0).not_null()),
-            ("instance_actor_id".to_string(), DatabaseType::String(0).not_null())
+            ("instance_actor_id".to_string(), DatabaseType::String(0).not_null()),
+            ("community_remote_id".to_string(), DatabaseType::I64.nullable()),
+            ("creator_remote_id".to_string(), DatabaseType::I64.nullable())
         ])
     }
 
@@ -52,7 +56,9 @@ impl DatabaseSchema for LemmyId {
         vec![
             &self.post_remote_id,
             &self.post_actor_id,
-            &self.instance_actor_id
+            &self.instance_actor_id,
+            &self.community_remote_id,
+            &self.creator_remote_id
         ]
     }
 }
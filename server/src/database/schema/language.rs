@@ -0,0 +1,62 @@
+use std::{
+    hash::Hash, 
+    collections::HashMap
+};
+use postgres::types::ToSql;
+use crate::api::lemmy::models::language::Language;
+use super::{
+    DatabaseSchema, 
+    DatabaseType
+};
+
+impl DatabaseSchema for Language {
+
+    fn get_table_name(
+
+    ) -> String {
+        "languages".to_string()
+    }
+
+    fn get_column_names(
+    
+    ) -> Vec<String> {
+        vec![
+            "code".to_string(),
+            "name".to_string()
+        ]
+    }
+
+    fn get_column_types(
+    
+    ) -> HashMap<String, DatabaseType> {
+        HashMap::from([
+            ("code".to_string(), DatabaseType::String(0).not_null()),
+            ("name".to_string(), DatabaseType::String(0).not_null())
+        ])
+    }
+
+    fn get_values(
+        &self
+    ) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.code,
+            &self.name
+        ]
+    }
+}
+
+impl PartialEq for Language {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code
+    }
+}
+
+impl Eq for Language {
+
+}
+
+impl Hash for Language {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.code.hash(state);
+    }
+}
@@ -0,0 +1,74 @@
+use std::{
+    hash::Hash, 
+    collections::HashMap
+};
+use postgres::types::ToSql;
+use super::{
+    DatabaseSchema, 
+    DatabaseType
+};
+
+#[derive(Debug, Clone)]
+pub struct Link {
+    pub post_ap_id : String,
+    pub url : String
+}
+
+impl DatabaseSchema for Link {
+
+    fn get_table_name(
+
+    ) -> String {
+        "links".to_string()
+    }
+
+    fn get_keys(
+    
+    ) -> Vec<String> {
+        Self::get_column_names()
+    }
+
+    fn get_column_names(
+    
+    ) -> Vec<String> {
+        vec![
+            "post_ap_id".to_string(),
+            "url".to_string(),
+        ]
+    }
+
+    fn get_column_types(
+    
+    ) -> HashMap<String, DatabaseType> {
+        HashMap::from([
+            ("post_ap_id".to_string(), DatabaseType::String(0).not_null()),
+            ("url".to_string(), DatabaseType::String(0).not_null()),
+        ])
+    }
+
+    fn get_values(
+        &self
+    ) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.post_ap_id,
+            &self.url
+        ]
+    }
+}
+
+impl PartialEq for Link {
+    fn eq(&self, other: &Self) -> bool {
+        self.post_ap_id == other.post_ap_id && self.url == other.url
+    }
+}
+
+impl Eq for Link {
+
+}
+
+impl Hash for Link {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.post_ap_id.hash(state);
+        self.url.hash(state);
+    }
+}
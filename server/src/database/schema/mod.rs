@@ -1,7 +1,14 @@
 pub mod author;
 pub mod community;
+pub mod community_stats;
 pub mod id;
+pub mod language;
+pub mod link;
+pub mod new_post;
+pub mod post_count;
 pub mod posts;
+pub mod saved_search;
+pub mod saved_search_post;
 pub mod site;
 pub mod word;
 pub mod xref;
@@ -112,6 +119,7 @@ pub enum DatabaseType {
     I16,
     I32,
     I64,
+    F32,
     String(i16),
     Uuid,
     DateTime,
@@ -130,6 +138,7 @@ impl DatabaseType {
             DatabaseType::I16 => "INT2".to_string(),
             DatabaseType::I32 => "INT4".to_string(),
             DatabaseType::I64 => "INT8".to_string(),
+            DatabaseType::F32 => "FLOAT4".to_string(),
             DatabaseType::String(n) => {
                 if n > &0 {
                     format!("VARCHAR({})", n)
@@ -0,0 +1,75 @@
+use std::{
+    hash::Hash,
+    collections::HashMap
+};
+use chrono::{
+    DateTime,
+    Utc
+};
+use postgres::types::ToSql;
+use super::{
+    DatabaseSchema,
+    DatabaseType
+};
+
+/**
+ * A post that was recently indexed for the first time, which saved searches are
+ * checked against until it's too old to be news.
+ */
+#[derive(Debug, Clone)]
+pub struct NewPost {
+    pub post_actor_id : String,
+    pub indexed : DateTime<Utc>
+}
+
+impl DatabaseSchema for NewPost {
+
+    fn get_table_name(
+
+    ) -> String {
+        "new_posts".to_string()
+    }
+
+    fn get_column_names(
+
+    ) -> Vec<String> {
+        vec![
+            "post_actor_id".to_string(),
+            "indexed".to_string()
+        ]
+    }
+
+    fn get_column_types(
+
+    ) -> HashMap<String, DatabaseType> {
+        HashMap::from([
+            ("post_actor_id".to_string(), DatabaseType::String(0).not_null()),
+            ("indexed".to_string(), DatabaseType::DateTime.not_null())
+        ])
+    }
+
+    fn get_values(
+        &self
+    ) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.post_actor_id,
+            &self.indexed
+        ]
+    }
+}
+
+impl PartialEq for NewPost {
+    fn eq(&self, other: &Self) -> bool {
+        self.post_actor_id == other.post_actor_id
+    }
+}
+
+impl Eq for NewPost {
+
+}
+
+impl Hash for NewPost {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.post_actor_id.hash(state);
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    hash::Hash,
+    collections::HashMap
+};
+use postgres::types::ToSql;
+use super::{
+    DatabaseSchema,
+    DatabaseType
+};
+
+/**
+ * How many posts have been indexed for a community or an author, kept up to date
+ * by the crawler so that searches don't need to count them.
+ */
+#[derive(Debug, Clone)]
+pub struct PostCount {
+    /// The actor id of either the community or the author.
+    pub actor_id : String,
+    pub posts : i64
+}
+
+impl DatabaseSchema for PostCount {
+
+    fn get_table_name(
+
+    ) -> String {
+        "post_counts".to_string()
+    }
+
+    fn get_column_names(
+
+    ) -> Vec<String> {
+        vec![
+            "actor_id".to_string(),
+            "posts".to_string()
+        ]
+    }
+
+    fn get_column_types(
+
+    ) -> HashMap<String, DatabaseType> {
+        HashMap::from([
+            ("actor_id".to_string(), DatabaseType::String(0).not_null()),
+            ("posts".to_string(), DatabaseType::I64.not_null())
+        ])
+    }
+
+    fn get_values(
+        &self
+    ) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.actor_id,
+            &self.posts
+        ]
+    }
+}
+
+impl PartialEq for PostCount {
+    fn eq(&self, other: &Self) -> bool {
+        self.actor_id == other.actor_id
+    }
+}
+
+impl Eq for PostCount {
+
+}
+
+impl Hash for PostCount {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.actor_id.hash(state);
+    }
+}
@@ -28,6 +28,14 @@ impl DatabaseSchema for PostData {
             "score".to_string(),
             "author_actor_id".to_string(),
             "community_ap_id".to_string(),
+            "language".to_string(),
+            "detected_language".to_string(),
+            "language_confidence".to_string(),
+            "domain".to_string(),
+            "normalized_url".to_string(),
+            "published".to_string(),
+            "updated".to_string(),
+            "comments".to_string(),
         ]
     }
 
@@ -41,7 +49,15 @@ impl DatabaseSchema for PostData {
             ("body".to_string(), DatabaseType::String(0).nullable()),
             ("score".to_string(), DatabaseType::I32.not_null()),
             ("author_actor_id".to_string(), DatabaseType::String(0).not_null()),
-            ("community_ap_id".to_string(), DatabaseType::String(0).not_null())
+            ("community_ap_id".to_string(), DatabaseType::String(0).not_null()),
+            ("language".to_string(), DatabaseType::String(0).nullable()),
+            ("detected_language".to_string(), DatabaseType::String(0).nullable()),
+            ("language_confidence".to_string(), DatabaseType::F32.nullable()),
+            ("domain".to_string(), DatabaseType::String(0).nullable()),
+            ("normalized_url".to_string(), DatabaseType::String(0).nullable()),
+            ("published".to_string(), DatabaseType::DateTime.nullable()),
+            ("updated".to_string(), DatabaseType::DateTime.nullable()),
+            ("comments".to_string(), DatabaseType::I64.nullable())
         ])
     }
 
@@ -55,7 +71,15 @@ impl DatabaseSchema for PostData {
             &self.post.body,
             &self.counts.score,
             &self.creator.actor_id,
-            &self.community.actor_id
+            &self.community.actor_id,
+            &self.post.language,
+            &self.post.detected_language,
+            &self.post.language_confidence,
+            &self.post.domain,
+            &self.post.normalized_url,
+            &self.post.published,
+            &self.post.updated,
+            &self.counts.comments
         ]
     }
 }
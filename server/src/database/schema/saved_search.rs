@@ -0,0 +1,98 @@
+use std::{
+    hash::Hash,
+    collections::HashMap
+};
+use chrono::{
+    DateTime,
+    Utc
+};
+use postgres::types::ToSql;
+use uuid::Uuid;
+use super::{
+    DatabaseSchema,
+    DatabaseType
+};
+
+/**
+ * A query that someone wants to be told about new matches for, along with where
+ * those matches should be sent.  The id is only ever given to whoever saved the
+ * search, and is needed to remove it again.
+ */
+#[derive(Debug, Clone)]
+pub struct SavedSearch {
+    pub id : Uuid,
+    pub query : String,
+    pub preferred_instance : String,
+    pub webhook_url : String,
+    /// One of 'webhook', 'ntfy' or 'matrix'.
+    pub format : String,
+    pub created : DateTime<Utc>,
+    pub last_notified : Option<DateTime<Utc>>
+}
+
+impl DatabaseSchema for SavedSearch {
+
+    fn get_table_name(
+
+    ) -> String {
+        "saved_searches".to_string()
+    }
+
+    fn get_column_names(
+
+    ) -> Vec<String> {
+        vec![
+            "id".to_string(),
+            "query".to_string(),
+            "preferred_instance".to_string(),
+            "webhook_url".to_string(),
+            "format".to_string(),
+            "created".to_string(),
+            "last_notified".to_string()
+        ]
+    }
+
+    fn get_column_types(
+
+    ) -> HashMap<String, DatabaseType> {
+        HashMap::from([
+            ("id".to_string(), DatabaseType::Uuid.not_null()),
+            ("query".to_string(), DatabaseType::String(0).not_null()),
+            ("preferred_instance".to_string(), DatabaseType::String(0).not_null()),
+            ("webhook_url".to_string(), DatabaseType::String(0).not_null()),
+            ("format".to_string(), DatabaseType::String(16).not_null()),
+            ("created".to_string(), DatabaseType::DateTime.not_null()),
+            ("last_notified".to_string(), DatabaseType::DateTime.nullable())
+        ])
+    }
+
+    fn get_values(
+        &self
+    ) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.id,
+            &self.query,
+            &self.preferred_instance,
+            &self.webhook_url,
+            &self.format,
+            &self.created,
+            &self.last_notified
+        ]
+    }
+}
+
+impl PartialEq for SavedSearch {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for SavedSearch {
+
+}
+
+impl Hash for SavedSearch {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
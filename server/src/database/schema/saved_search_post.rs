@@ -0,0 +1,79 @@
+use std::{
+    hash::Hash,
+    collections::HashMap
+};
+use postgres::types::ToSql;
+use uuid::Uuid;
+use super::{
+    DatabaseSchema,
+    DatabaseType
+};
+
+/**
+ * A new post that has already been sent to a saved search, so that it's never
+ * sent twice.
+ */
+#[derive(Debug, Clone)]
+pub struct SavedSearchPost {
+    pub saved_search_id : Uuid,
+    pub post_actor_id : String
+}
+
+impl DatabaseSchema for SavedSearchPost {
+
+    fn get_table_name(
+
+    ) -> String {
+        "saved_search_posts".to_string()
+    }
+
+    fn get_keys(
+
+    ) -> Vec<String> {
+        Self::get_column_names()
+    }
+
+    fn get_column_names(
+
+    ) -> Vec<String> {
+        vec![
+            "saved_search_id".to_string(),
+            "post_actor_id".to_string()
+        ]
+    }
+
+    fn get_column_types(
+
+    ) -> HashMap<String, DatabaseType> {
+        HashMap::from([
+            ("saved_search_id".to_string(), DatabaseType::Uuid.not_null()),
+            ("post_actor_id".to_string(), DatabaseType::String(0).not_null())
+        ])
+    }
+
+    fn get_values(
+        &self
+    ) -> Vec<&(dyn ToSql + Sync)> {
+        vec![
+            &self.saved_search_id,
+            &self.post_actor_id
+        ]
+    }
+}
+
+impl PartialEq for SavedSearchPost {
+    fn eq(&self, other: &Self) -> bool {
+        self.saved_search_id == other.saved_search_id && self.post_actor_id == other.post_actor_id
+    }
+}
+
+impl Eq for SavedSearchPost {
+
+}
+
+impl Hash for SavedSearchPost {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.saved_search_id.hash(state);
+        self.post_actor_id.hash(state);
+    }
+}
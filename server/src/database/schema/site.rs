@@ -19,7 +19,9 @@ pub struct Site {
     pub actor_id : String,
     pub last_post_page : i32,
     pub last_comment_page : i32,
-    pub last_update : DateTime<Utc>
+    pub last_update : DateTime<Utc>,
+    /// The language that most of the instance's own posts are written in.
+    pub language : Option<String>
 }
 
 // impl Site {
@@ -55,6 +57,7 @@ impl DatabaseSchema for Site {
             "last_post_page".to_string(),
             "last_comment_page".to_string(),
             "last_update".to_string(),
+            "language".to_string(),
         ]
     }
 
@@ -67,7 +70,8 @@ impl DatabaseSchema for Site {
             ("actor_id".to_string(), DatabaseType::String(0).not_null().unique()),
             ("last_post_page".to_string(), DatabaseType::I32.not_null()),
             ("last_comment_page".to_string(), DatabaseType::I32.not_null()),
-            ("last_update".to_string(), DatabaseType::DateTime.not_null())
+            ("last_update".to_string(), DatabaseType::DateTime.not_null()),
+            ("language".to_string(), DatabaseType::String(0).nullable())
         ])
     }
 
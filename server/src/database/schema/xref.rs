@@ -12,7 +12,9 @@ use super::{
 #[derive(Debug, Clone)]
 pub struct Search {
     pub word_id : Uuid,
-    pub post_ap_id : String
+    pub post_ap_id : String,
+    /// The flags of each `IndexField` that the word was found in.
+    pub fields : i16
 }
 
 impl DatabaseSchema for Search {
@@ -26,7 +28,10 @@ impl DatabaseSchema for Search {
     fn get_keys(
     
     ) -> Vec<String> {
-        Self::get_column_names()
+        vec![
+            "word_id".to_string(),
+            "post_ap_id".to_string(),
+        ]
     }
 
     fn get_column_names(
@@ -35,6 +40,7 @@ impl DatabaseSchema for Search {
         vec![
             "word_id".to_string(),
             "post_ap_id".to_string(),
+            "fields".to_string(),
         ]
     }
 
@@ -44,6 +50,7 @@ impl DatabaseSchema for Search {
         HashMap::from([
             ("word_id".to_string(), DatabaseType::Uuid.not_null()),
             ("post_ap_id".to_string(), DatabaseType::String(0).not_null()),
+            ("fields".to_string(), DatabaseType::I16.nullable()),
         ])
     }
 
@@ -52,7 +59,8 @@ impl DatabaseSchema for Search {
     ) -> Vec<&(dyn ToSql + Sync)> {
         vec![
             &self.word_id,
-            &self.post_ap_id
+            &self.post_ap_id,
+            &self.fields
         ]
     }
 }
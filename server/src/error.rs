@@ -13,6 +13,7 @@ pub enum LemmySearchError {
     Generic(&'static str),
     Unknown(String),
     IO(std::io::Error),
+    Config(::config::ConfigError),
     Database(postgres::Error),
     DatabaseConnection(r2d2_postgres::r2d2::Error),
     Network(reqwest::Error),
@@ -29,6 +30,7 @@ impl std::fmt::Display for LemmySearchError {
             Self::Generic(string) => write!(f, "Error '{}'", string),
             Self::Unknown(string) => write!(f, "Unknown Error '{}'", string),
             Self::IO(err) => err.fmt(f),
+            Self::Config(err) => err.fmt(f),
             Self::Database(postgres) => postgres.fmt(f),
             Self::DatabaseConnection(r2d2_postgres) => r2d2_postgres.fmt(f),
             Self::Network(reqwest) => reqwest.fmt(f),
@@ -57,6 +59,12 @@ impl From<std::io::Error> for LemmySearchError {
     }
 }
 
+impl From<::config::ConfigError> for LemmySearchError {
+    fn from(value: ::config::ConfigError) -> Self {
+        LemmySearchError::Config(value)
+    }
+}
+
 impl From<PoolError<<Manager<PostgresConnectionManager<NoTls>> as deadpool::managed::Manager>::Error>> for LemmySearchError {
     fn from(value:PoolError<<Manager<PostgresConnectionManager<NoTls>> as deadpool::managed::Manager>::Error>) -> Self {
         LemmySearchError::DatabasePoolError(value)
@@ -1,20 +1,34 @@
 mod api;
+mod cli;
 mod config;
 mod crawler;
 mod database;
 mod error;
 
-use std::{
-    env, 
-    sync::Mutex, time::Duration
-};
+use std::sync::Mutex;
 use actix_files as fs;
 use actix_web::{
     App, 
     HttpServer,
     web::Data
 };
-use api::search::SearchHandler;
+use api::{
+    compat::LemmyCompatHandler,
+    feed::FeedHandler,
+    opensearch::OpenSearchHandler,
+    saved_search::SavedSearchHandler,
+    search::SearchHandler,
+    v1::ApiV1Handler
+};
+use clap::Parser;
+use cli::{
+    Args, 
+    Command
+};
+use config::{
+    Config, 
+    handle::ConfigHandle
+};
 use crawler::Runner;
 use database::Database;
 
@@ -22,67 +36,117 @@ use database::Database;
 async fn main() -> std::io::Result<()> {
     env_logger::init();
 
-    let args: Vec<String> = env::args().collect();
-    let ui_directory = match args.get(1) {
-        Some(path) => path,
-        None => "./ui"
-    }.to_owned();
-
-    let config = config::Config::load();
+    let args = Args::parse();
 
-    println!("Giving time for database to come online...");
-    async_std::task::sleep(Duration::from_secs( 1 )).await;
-
-    let database = match Database::create(&config.postgres).await {
+    let config = match Config::load(args.config.as_deref()) {
         Ok(value) => value,
         Err(err) => {
-            println!("Database pool creation failed...");
-            if config.postgres.log {
-                println!("{}", err);
-            }
-            panic!();
+            println!("Failed to load config...");
+            println!("{}", err);
+            std::process::exit(1);
         }
     };
 
-    let d = database.clone();
-
-    let init_result = d.init_database().await;
-    
-    match init_result {
-        Ok(_) => {}
+    let database = match Database::connect_with_retry(&config.postgres).await {
+        Ok(value) => value,
         Err(err) => {
-            println!("Database initialization failed...");
-            if config.postgres.log {
+            println!(
+                "Unable to connect to the database at '{}:{}'; giving up...", 
+                config.postgres.hostname, 
+                config.postgres.port
+            );
+            println!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    match args.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(ConfigHandle::new(config, args.config), database, !args.no_ui).await,
+        command => {
+            if let Err(err) = command.execute(&config, database).await {
                 println!("{}", err);
+                std::process::exit(1);
             }
-            panic!();
+            Ok(())
         }
     }
+}
+
+async fn serve(
+    config_handle : ConfigHandle,
+    database : Database,
+    serve_ui : bool
+) -> std::io::Result<()> {
+    let config = config_handle.get();
 
-    let mut cralwer_runner = Runner::new(&config.crawler, database.clone());
+    let config_watcher = config_handle.watch();
+
+    let mut cralwer_runner = Runner::new(&config_handle, database.clone());
     cralwer_runner.start();
 
     let pool = Data::new(Mutex::new(database.pool.clone()));
+    let config_data = Data::new(config_handle);
+
+    let server_config = config.server.clone();
 
     let factory = move || {
         let search_handler = SearchHandler::new(&config);
         let mut app = App::new()
-            .app_data(pool.clone());
+            .app_data(pool.clone())
+            .app_data(config_data.clone());
         for (path, route) in search_handler.routes {
             app = app.route(path.as_str(), route);
         }
-        app.service(
-            fs::Files::new("/", &ui_directory)
-                .index_file("index.html")
-        )
+        for (path, route) in ApiV1Handler::new().routes {
+            app = app.route(path.as_str(), route);
+        }
+        for (path, route) in OpenSearchHandler::new().routes {
+            app = app.route(path.as_str(), route);
+        }
+        for (path, route) in FeedHandler::new().routes {
+            app = app.route(path.as_str(), route);
+        }
+        for (path, route) in SavedSearchHandler::new(&config).routes {
+            app = app.route(path.as_str(), route);
+        }
+        for (path, route) in LemmyCompatHandler::new().routes {
+            app = app.route(path.as_str(), route);
+        }
+        if serve_ui {
+            app = app.service(
+                fs::Files::new("/", &config.server.ui_directory)
+                    .index_file("index.html")
+            );
+        }
+        app
+    };
+
+    let mut server = HttpServer::new(factory);
+    if let Some(workers) = server_config.workers {
+        server = server.workers(workers);
+    }
+
+    // When a unix socket is configured, it's used instead of the TCP listener.
+    let server = match &server_config.unix_socket {
+        Some(path) => {
+            println!("Listening on unix socket '{}'...", path);
+            server.bind_uds(path)?
+        },
+        None => {
+            println!("Listening on '{}:{}'...", server_config.bind_address, server_config.port);
+            server.bind((server_config.bind_address.as_str(), server_config.port))?
+        }
     };
+    if !serve_ui {
+        println!("Running in API only mode; the UI will not be served.");
+    }
 
-    let result = HttpServer::new(factory)
-        .bind(("0.0.0.0", 8000))?
+    let result = server
         .run()
         .await;
 
     cralwer_runner.stop();
+    config_watcher.abort();
 
     result
 }
@@ -11,11 +11,21 @@ $(document).ready(function() {
         }
     });
 
+    $("#search").on("input", function() {
+        populateSuggestions();
+    });
+
     $("#instance-select").on("change", function() {
         preferred_instance = this.value;
         setCookie("preferred-instance", preferred_instance);
     });
 
+    $("#language-select").on("change", function() {
+        language = this.value;
+        setCookie("language", language);
+    });
+
     getVersion();
     populateInstances();
+    populateLanguages();
 });
@@ -7,7 +7,7 @@ function checkQueryParameters() {
 }
 
 function query(queryString) {
-    fetchJson("/search" + queryString, result => {
+    fetchJson("/search" + queryString + "&facets=5", result => {
 
         let response_time = Math.round((result.time_taken.secs + (result.time_taken.nanos / 1_000_000_000)) * 100) / 100;
 
@@ -18,17 +18,103 @@ function query(queryString) {
         let list = $("<ol/>");
 
         result.posts.forEach(post => {
-            let item = buildSearchResult(post, result.original_query_terms);
+            let item = buildSearchResult(post);
             list.append(item);
         });
         $("#results").empty();
+        if(result.did_you_mean != null) {
+            $("#results").append(buildDidYouMean(result.did_you_mean));
+        }
+        if(result.facets != null) {
+            $("#results").append(buildFacets(result.facets));
+        }
         $("#results").append(list);
 
-        buildPageControls(result.total_pages);
+        buildPageControls(result.next);
     })
 }
 
-function buildPageControls(total_pages) {
+function addFeedLinks(urlParameters) {
+    // Lets feed readers and browsers find a feed of the newest posts for this query.
+    let params = new URLSearchParams();
+    ["query", "preferred_instance", "languages"].forEach(name => {
+        if(urlParameters.has(name)) {
+            params.set(name, urlParameters.get(name));
+        }
+    });
+
+    let feeds = {
+        "application/rss+xml" : "/search.rss",
+        "application/atom+xml" : "/search.atom"
+    };
+    Object.entries(feeds).forEach(([type, path]) => {
+        let link = $("<link />")
+            .attr("rel", "alternate")
+            .attr("type", type)
+            .attr("title", urlParameters.get("query"))
+            .attr("href", path + "?" + params.toString());
+        $("head").append(link);
+    });
+}
+
+function buildDidYouMean(did_you_mean) {
+    let params = new URLSearchParams(window.location.search);
+    params.set("query", did_you_mean);
+    params.set("page", 1);
+
+    let container = $("<div/>")
+        .addClass("did-you-mean");
+    container.text("Did you mean: ");
+
+    let link = $("<a/>")
+        .attr("href", "/results?" + params.toString());
+    link.text(did_you_mean);
+    container.append(link);
+    return container;
+}
+
+function buildFacets(facets) {
+    const urlParameters = new URLSearchParams(window.location.search);
+    let query = urlParameters.get("query");
+
+    let groups = {
+        "Communities" : facets.communities,
+        "Instances" : facets.instances,
+        "Authors" : facets.authors,
+        "Languages" : facets.languages
+    };
+
+    let container = $("<div/>")
+        .addClass("facets");
+    Object.entries(groups).forEach(([title, values]) => {
+        // Only worth offering a filter if it would actually narrow the results.
+        if(values.length < 2) {
+            return;
+        }
+
+        let group = $("<div/>")
+            .addClass("facet-group");
+        let heading = $("<span/>")
+            .addClass("facet-title");
+        heading.text(title + ": ");
+        group.append(heading);
+
+        values.forEach(facet => {
+            let params = new URLSearchParams(window.location.search);
+            params.set("query", query + " " + facet.filter);
+            params.set("page", 1);
+
+            let link = $("<a/>")
+                .attr("href", "/results?" + params.toString());
+            link.text(facet.name + " (" + facet.count + ")");
+            group.append(link);
+        });
+        container.append(group);
+    });
+    return container;
+}
+
+function buildPageControls(next_cursor) {
     const urlParameters = new URLSearchParams(window.location.search);
     let query = urlParameters.get("query");
     let page = Math.max(parseInt(urlParameters.get("page"), 10) || 1, 1);
@@ -42,6 +128,9 @@ function buildPageControls(total_pages) {
             "preferred_instance" : dropSchema(preferred_instance),
             "page" : page - 1
         };
+        if(urlParameters.has("languages")) {
+            params["languages"] = urlParameters.get("languages");
+        }
         
         let href = "/results?" + new URLSearchParams(params).toString();
 
@@ -52,15 +141,21 @@ function buildPageControls(total_pages) {
 
         page_control.append(previous);
     }
-    if(page > 1 && page < total_pages) {
+    if(page > 1 && next_cursor) {
         page_control.append($("<span> | </span>"));
     }
-    if(page < total_pages) {
+    if(next_cursor) {
+        // The cursor carries on from exactly where this page ended, the page
+        // number is only kept to link back to the previous page.
         let params = {
             "query" : query,
             "preferred_instance" : dropSchema(preferred_instance),
-            "page" : page + 1
+            "page" : page + 1,
+            "cursor" : next_cursor
         };
+        if(urlParameters.has("languages")) {
+            params["languages"] = urlParameters.get("languages");
+        }
         
         let href = "/results?" + new URLSearchParams(params).toString();
 
@@ -73,7 +168,7 @@ function buildPageControls(total_pages) {
     }
 }
 
-function buildSearchResult(post, original_query_terms) {
+function buildSearchResult(post) {
     let item = $("<li/>")
         .addClass("search-result");
     if (post.ur && isImage(post.url)) {
@@ -134,43 +229,62 @@ function buildSearchResult(post, original_query_terms) {
 
     item.append(post_citation);
 
+    if(post.also_posted_in.length > 0) {
+        item.append(buildCrossPosts(post.also_posted_in));
+    }
+
     let post_body = $("<p>/")
         .addClass("post-body");
-    if(post.body != null) {
-        post_body.append(getPostQueryBody(original_query_terms, post.body));
+    if(post.snippet != null) {
+        post_body.append(getPostSnippet(post.snippet, post.highlights));
     }
     item.append(post_body);
 
     return item;
 }
 
-function getPostQueryBody(queryTerms, body) {
-    let regex = "(\s" + queryTerms.join("\s)|(\s") + "\s)";
-    let split_body = body.split(new RegExp(regex, "ig"))
-        .filter(token => token != null);
-    var length = 0;
-    let spans = split_body.map(token => {
+function buildCrossPosts(cross_posts) {
+    let container = $("<div/>")
+        .addClass("post-cross-posts");
+    container.text("Also posted in: ");
 
-        if (length >= 200) {
-            return null;
+    cross_posts.forEach((cross_post, index) => {
+        if(index > 0) {
+            container.append($("<span>, </span>"));
         }
 
-        let span = $("<span />");
-        if (queryTerms.includes(token)) {
-            span.addClass("search-term");
+        let link = $("<a/>")
+            .attr("href", preferred_instance + "post/" + cross_post.remote_id);
+        link.text(cross_post.community.title ?? cross_post.community.name);
+        container.append(link);
+    });
+    return container;
+}
+
+function getPostSnippet(snippet, highlights) {
+    // The highlights are in characters, not UTF-16 code units.
+    let characters = Array.from(snippet);
+    let spans = [];
+    var position = 0;
+    highlights.forEach(highlight => {
+        if (highlight.start > position) {
+            let span = $("<span />");
+            span.text(characters.slice(position, highlight.start).join(""));
+            spans.push(span);
         }
 
-        let sub = Math.min(200 - length, token.length);
-        span.text(token.substring(0, sub));
-        length += sub;
+        let span = $("<span />")
+            .addClass("search-term");
+        span.text(characters.slice(highlight.start, highlight.end).join(""));
+        spans.push(span);
 
-        return span;
-    }).filter(span => span != null);
+        position = highlight.end;
+    });
 
-    if(spans.length < split_body.length) {
-        let more = $("<span />");
-        more.text("...");
-        spans.push(more);
+    if (position < characters.length) {
+        let span = $("<span />");
+        span.text(characters.slice(position).join(""));
+        spans.push(span);
     }
     return spans;
 }
@@ -204,13 +318,38 @@ $(document).ready(function() {
     });
 
 
+    $("#search").on("input", function() {
+        populateSuggestions();
+    });
+
     $("#instance-select").on("change", function() {
         preferred_instance = this.value;
         setCookie("preferred-instance", preferred_instance);
     });
 
+    $("#language-select").on("change", function() {
+        language = this.value;
+        setCookie("language", language);
+    });
+
     getVersion();
-    populateInstances();
+    populateLanguages();
 
-    query(window.location.search);
+    const urlParameters = new URLSearchParams(window.location.search);
+    if(urlParameters.has("preferred_instance")) {
+        populateInstances();
+        addFeedLinks(urlParameters);
+        query(window.location.search);
+    } else {
+        // Searches from the browser's address bar only include the query, so
+        // fill in the rest the same way as searching from the home page would.
+        populateInstances(() => {
+            urlParameters.set("preferred_instance", dropSchema(preferred_instance));
+            let language = getCookie("language");
+            if(language && !urlParameters.has("languages")) {
+                urlParameters.set("languages", language);
+            }
+            window.location.replace("/results?" + urlParameters.toString());
+        });
+    }
 });
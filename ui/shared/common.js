@@ -1,6 +1,7 @@
 var preferred_instance = null;
+var language = null;
 
-function populateInstances() {
+function populateInstances(onLoaded) {
     fetchJson("/instances", result => {
 
         preferred_instance = getCookie("preferred-instance") || result[0].site.actor_id;
@@ -17,6 +18,58 @@ function populateInstances() {
 
             select.append(option);
         })
+
+        if(onLoaded) {
+            onLoaded();
+        }
+    })
+}
+
+function populateLanguages() {
+    fetchJson("/languages", result => {
+
+        language = getCookie("language") || "";
+        if(!result.map(item => item.code).includes(language)) {
+            language = "";
+        }
+
+        let select = $("#language-select");
+        let any = $("<option />")
+            .attr("value", "")
+            .prop("selected", language == "");
+        any.text("Any language");
+        select.append(any);
+
+        result.forEach(item => {
+            let option = $("<option />")
+                .attr("value", item.code)
+                .prop("selected", item.code == language);
+            option.text(item.name);
+
+            select.append(option);
+        })
+    })
+}
+
+function populateSuggestions() {
+    let prefix = $("#search").val();
+    if(!prefix || /\s$/.test(prefix)) {
+        return;
+    }
+
+    fetchJson("/suggest?" + new URLSearchParams({ "prefix" : prefix }).toString(), result => {
+        // The user may have kept typing while this was loading.
+        if($("#search").val() != prefix) {
+            return;
+        }
+
+        let list = $("#suggestions");
+        list.empty();
+        result.forEach(suggestion => {
+            let option = $("<option />")
+                .attr("value", suggestion.text);
+            list.append(option);
+        })
     })
 }
 
@@ -32,6 +85,9 @@ function onSearch() {
         "preferred_instance" : dropSchema(preferred_instance),
         "page" : 1
     };
+    if(language) {
+        params["languages"] = language;
+    }
     
     window.location = "/results?" + new URLSearchParams(params).toString();
 }